@@ -0,0 +1,78 @@
+// Copyright 2023 Lance Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ffi::CStr;
+
+use libduckdb_sys::{
+    duckdb_append_data_chunk, duckdb_appender, duckdb_appender_destroy, duckdb_appender_error,
+    duckdb_appender_flush, duckdb_state_DuckDBError,
+};
+use crate::{DataChunk, Error, Result};
+
+/// Appends rows to a table, created through [crate::Connection::appender].
+///
+/// It must be dropped before the connection it was created from.
+#[derive(Debug)]
+pub struct Appender {
+    ptr: duckdb_appender,
+}
+
+// An appender can be used from any thread, though by one at a time.
+unsafe impl Send for Appender {}
+
+impl From<duckdb_appender> for Appender {
+    fn from(ptr: duckdb_appender) -> Self {
+        Self { ptr }
+    }
+}
+
+impl Drop for Appender {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe {
+                duckdb_appender_destroy(&mut self.ptr);
+            }
+        }
+        self.ptr = std::ptr::null_mut();
+    }
+}
+
+impl Appender {
+    /// Append the rows of `chunk`, whose columns must match those of the table.
+    pub fn append_chunk(&mut self, chunk: &DataChunk) -> Result<()> {
+        let state = unsafe { duckdb_append_data_chunk(self.ptr, chunk.ptr) };
+        if state == duckdb_state_DuckDBError {
+            return Err(self.error());
+        }
+        Ok(())
+    }
+
+    /// Write the rows appended so far to the table.
+    pub fn flush(&mut self) -> Result<()> {
+        let state = unsafe { duckdb_appender_flush(self.ptr) };
+        if state == duckdb_state_DuckDBError {
+            return Err(self.error());
+        }
+        Ok(())
+    }
+
+    fn error(&self) -> Error {
+        let message = unsafe { duckdb_appender_error(self.ptr) };
+        if message.is_null() {
+            return Error::DuckDB("appender error".to_string());
+        }
+        let message = unsafe { CStr::from_ptr(message) };
+        Error::DuckDB(message.to_string_lossy().into_owned())
+    }
+}
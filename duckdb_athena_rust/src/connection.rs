@@ -12,8 +12,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use libduckdb_sys::{duckdb_connection, duckdb_register_table_function};
+use std::ffi::{CStr, CString};
+
+use libduckdb_sys::{
+    duckdb_appender, duckdb_appender_create, duckdb_appender_destroy, duckdb_appender_error,
+    duckdb_connection, duckdb_destroy_prepare, duckdb_destroy_result, duckdb_disconnect,
+    duckdb_prepare, duckdb_prepare_error, duckdb_prepared_statement, duckdb_query,
+    duckdb_register_scalar_function, duckdb_register_table_function, duckdb_result,
+    duckdb_result_error, duckdb_state_DuckDBError,
+};
+use crate::ffi::duckdb_ext_get_config_option;
+use crate::scalar_function::ScalarFunction;
 use crate::table_function::TableFunction;
+use crate::vtab::{self, VTab};
+use crate::{Appender, Error, PreparedStatement, QueryResult, Result, Value};
 
 /// A connection to a database. This represents a (client) connection that can
 /// be used to query the database.
@@ -22,13 +34,115 @@ pub struct Connection {
     ptr: duckdb_connection,
 }
 
+// A connection can be used from any thread, though by one at a time.
+unsafe impl Send for Connection {}
+
 impl From<duckdb_connection> for Connection {
     fn from(ptr: duckdb_connection) -> Self {
         Self { ptr }
     }
 }
 
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe {
+                duckdb_disconnect(&mut self.ptr);
+            }
+        }
+        self.ptr = std::ptr::null_mut();
+    }
+}
+
 impl Connection {
+    /// Run a SQL query on this connection and return its (materialized) result.
+    pub fn query(&self, sql: &str) -> Result<QueryResult> {
+        let c_sql = CString::new(sql).map_err(|e| Error::DuckDB(e.to_string()))?;
+        let mut result: duckdb_result = unsafe { std::mem::zeroed() };
+
+        let state = unsafe { duckdb_query(self.ptr, c_sql.as_ptr(), &mut result) };
+        if state == duckdb_state_DuckDBError {
+            let message = unsafe { CStr::from_ptr(duckdb_result_error(&mut result)) }
+                .to_string_lossy()
+                .into_owned();
+            unsafe { duckdb_destroy_result(&mut result) };
+            return Err(Error::DuckDB(message));
+        }
+
+        Ok(QueryResult::from(result))
+    }
+
+    /// Run a SQL query on this connection, streaming its result instead of
+    /// materializing it, see [PreparedStatement::execute_streaming].
+    pub fn query_streaming(&self, sql: &str) -> Result<QueryResult> {
+        self.prepare(sql)?.execute_streaming()
+    }
+
+    /// Prepare a SQL statement with `?` parameters to be executed later.
+    pub fn prepare(&self, sql: &str) -> Result<PreparedStatement> {
+        let c_sql = CString::new(sql).map_err(|e| Error::DuckDB(e.to_string()))?;
+        let mut statement: duckdb_prepared_statement = std::ptr::null_mut();
+
+        let state = unsafe { duckdb_prepare(self.ptr, c_sql.as_ptr(), &mut statement) };
+        if state == duckdb_state_DuckDBError {
+            let message = unsafe { CStr::from_ptr(duckdb_prepare_error(statement)) }
+                .to_string_lossy()
+                .into_owned();
+            unsafe { duckdb_destroy_prepare(&mut statement) };
+            return Err(Error::DuckDB(message));
+        }
+
+        Ok(PreparedStatement::from(statement))
+    }
+
+    /// Run a SQL statement with `params` bound to its `?` parameters.
+    pub fn execute(&self, sql: &str, params: &[Value]) -> Result<QueryResult> {
+        let statement = self.prepare(sql)?;
+        for (idx, param) in params.iter().enumerate() {
+            statement.bind(idx + 1, param)?;
+        }
+        statement.execute()
+    }
+
+    /// Create an [Appender] for the table `table`, looked up in `schema` if given.
+    pub fn appender(&self, schema: Option<&str>, table: &str) -> Result<Appender> {
+        let c_schema = schema
+            .map(CString::new)
+            .transpose()
+            .map_err(|e| Error::DuckDB(e.to_string()))?;
+        let c_table = CString::new(table).map_err(|e| Error::DuckDB(e.to_string()))?;
+        let mut appender: duckdb_appender = std::ptr::null_mut();
+
+        let state = unsafe {
+            duckdb_appender_create(
+                self.ptr,
+                c_schema.as_ref().map_or(std::ptr::null(), |s| s.as_ptr()),
+                c_table.as_ptr(),
+                &mut appender,
+            )
+        };
+        if state == duckdb_state_DuckDBError {
+            let message = unsafe { CStr::from_ptr(duckdb_appender_error(appender)) }
+                .to_string_lossy()
+                .into_owned();
+            unsafe { duckdb_appender_destroy(&mut appender) };
+            return Err(Error::DuckDB(message));
+        }
+
+        Ok(Appender::from(appender))
+    }
+
+    /// The current value of a configuration option, `None` if there is no such option.
+    pub fn config_option(&self, name: &str) -> Option<Value> {
+        let c_name = CString::new(name).ok()?;
+        let ptr = unsafe { duckdb_ext_get_config_option(self.ptr, c_name.as_ptr()) };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Value::from(ptr))
+        }
+    }
+
     pub fn register_table_function(
         &self,
         table_function: TableFunction,
@@ -38,4 +152,106 @@ impl Connection {
         }
         Ok(())
     }
+
+    /// Register the table function implemented by `T` under `name`.
+    pub fn register_vtab<T: VTab>(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.register_table_function(vtab::table_function::<T>(name))
+    }
+
+    pub fn register_scalar_function(
+        &self,
+        scalar_function: ScalarFunction,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let state = unsafe { duckdb_register_scalar_function(self.ptr, scalar_function.ptr) };
+        if state == duckdb_state_DuckDBError {
+            return Err("could not register scalar function".into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, LogicalTypeId};
+
+    fn ok<T>(result: Result<T>) -> T {
+        result.unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[test]
+    fn test_prepare_and_execute() {
+        let connection = ok(ok(Database::open_in_memory()).connect());
+        ok(connection.query("CREATE TABLE numbers (i BIGINT, name VARCHAR)"));
+
+        let insert = ok(connection.prepare("INSERT INTO numbers VALUES (?, ?)"));
+        assert_eq!(insert.num_parameters(), 2);
+        for (i, name) in [(1, "one"), (2, "two"), (3, "three")] {
+            ok(insert.bind(1, &Value::from(i as i64)));
+            ok(insert.bind(2, &Value::from(name)));
+            ok(insert.execute());
+        }
+        assert!(insert.bind(3, &Value::from(4i64)).is_err());
+
+        let mut result = ok(connection.execute(
+            "SELECT i, name FROM numbers WHERE i >= ? ORDER BY i",
+            &[Value::from(2i64)],
+        ));
+        assert_eq!(result.num_columns(), 2);
+        assert_eq!(result.column_name(0), "i");
+        assert_eq!(result.column_name(1), "name");
+        assert_eq!(result.column_type(0).id(), LogicalTypeId::Bigint);
+        assert_eq!(result.column_type(1).id(), LogicalTypeId::Varchar);
+
+        let rows: Vec<(i64, String)> = result
+            .rows()
+            .iter()
+            .map(|row| (row[0].to_i64(), row[1].to_varchar()))
+            .collect();
+        assert_eq!(rows, vec![(2, "two".to_string()), (3, "three".to_string())]);
+        assert!(result.rows().is_empty());
+    }
+
+    #[test]
+    fn test_query_streaming() {
+        let connection = ok(ok(Database::open_in_memory()).connect());
+        let mut result = ok(connection.query_streaming("SELECT * FROM range(5000)"));
+        assert_eq!(result.num_columns(), 1);
+
+        let mut lens = Vec::new();
+        let mut sum = 0;
+        while let Some(chunk) = ok(result.try_fetch_chunk()) {
+            lens.push(chunk.len());
+            sum += (0..chunk.len())
+                .map(|row| chunk.value(0, row).to_i64())
+                .sum::<i64>();
+        }
+        assert!(lens.len() > 1);
+        assert_eq!(lens.iter().sum::<usize>(), 5000);
+        assert_eq!(sum, (0..5000).sum());
+        drop(result);
+
+        // Errors while producing rows fail the fetch rather than end the result,
+        // unless they come up before the first chunk
+        let sql = "SELECT CASE WHEN i < 4000 THEN i ELSE error('boom') END FROM range(5000) t(i)";
+        let error = match connection.query_streaming(sql) {
+            Err(e) => e,
+            Ok(mut result) => loop {
+                match result.try_fetch_chunk() {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => panic!("the result ended without an error"),
+                    Err(e) => break e,
+                }
+            },
+        };
+        assert!(error.to_string().contains("boom"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let connection = ok(ok(Database::open_in_memory()).connect());
+        assert!(connection.query("SELECT * FROM missing").is_err());
+        assert!(connection.prepare("SELEC 1").is_err());
+        assert!(connection.execute("SELECT ?", &[]).is_err());
+    }
 }
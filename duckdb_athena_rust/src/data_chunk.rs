@@ -12,18 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::vector::{FlatVector, ListVector, StructVector};
+use std::ffi::CStr;
+
+use super::vector::{FlatVector, ListVector, MapVector, StructVector};
 use libduckdb_sys::{
     duckdb_create_data_chunk, duckdb_data_chunk, duckdb_data_chunk_get_size,
     duckdb_data_chunk_get_vector, duckdb_data_chunk_set_size, duckdb_destroy_data_chunk,
-    duckdb_data_chunk_get_column_count,
+    duckdb_data_chunk_get_column_count, duckdb_free,
 };
-use crate::LogicalType;
+use crate::ffi::{duckdb_ext_data_chunk_cast_into, duckdb_ext_data_chunk_get_value};
+use crate::{Error, LogicalType, Result, Value};
 
 /// DataChunk in DuckDB.
 pub struct DataChunk {
     /// Pointer to the DataChunk in duckdb C API.
-    ptr: duckdb_data_chunk,
+    pub(crate) ptr: duckdb_data_chunk,
 
     /// Whether this [DataChunk] own the [DataChunk::ptr].
     owned: bool,
@@ -48,6 +51,11 @@ impl DataChunk {
         ListVector::from(unsafe { duckdb_data_chunk_get_vector(self.ptr, idx as u64) })
     }
 
+    /// Get the map vector at the column index: `idx`.
+    pub fn map_vector(&self, idx: usize) -> MapVector {
+        MapVector::from(unsafe { duckdb_data_chunk_get_vector(self.ptr, idx as u64) })
+    }
+
     /// Get struct vector at the column index: `idx`.
     pub fn struct_vector(&self, idx: usize) -> StructVector {
         StructVector::from(unsafe { duckdb_data_chunk_get_vector(self.ptr, idx as u64) })
@@ -70,6 +78,43 @@ impl DataChunk {
     pub fn num_columns(&self) -> usize {
         unsafe { duckdb_data_chunk_get_column_count(self.ptr) as usize }
     }
+
+    /// Copy the value at `row` of column `idx` out of the chunk.
+    pub fn value(&self, idx: usize, row: usize) -> Value {
+        assert!(idx < self.num_columns() && row < self.len());
+        Value::from(unsafe { duckdb_ext_data_chunk_get_value(self.ptr, idx as u64, row as u64) })
+    }
+
+    /// Move the rows of this chunk into `target`, which must have the same
+    /// number of columns.
+    ///
+    /// Columns whose types already match are referenced without copying, the
+    /// others are cast to the type of the target column. Values that aren't in
+    /// the dictionary of an `ENUM` target column become NULL, other values that
+    /// can't be cast fail the cast.
+    pub fn cast_into(&self, target: &DataChunk) -> Result<()> {
+        if self.num_columns() != target.num_columns() {
+            return Err(Error::DuckDB(format!(
+                "can't cast {} columns into {}",
+                self.num_columns(),
+                target.num_columns()
+            )));
+        }
+        let error = unsafe { duckdb_ext_data_chunk_cast_into(self.ptr, target.ptr) };
+        if error.is_null() {
+            return Ok(());
+        }
+        let message = unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned();
+        unsafe { duckdb_free(error.cast()) };
+        Err(Error::DuckDB(message))
+    }
+
+    /// Take ownership of a chunk handed out by the DuckDB C API.
+    pub(crate) fn from_owned(ptr: duckdb_data_chunk) -> Self {
+        Self { ptr, owned: true }
+    }
 }
 
 impl From<duckdb_data_chunk> for DataChunk {
@@ -12,13 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use libduckdb_sys::{duckdb_connect, duckdb_connection, duckdb_database, duckdb_state_DuckDBError};
-use crate::{Connection, Error, Result};
+use std::ffi::{CStr, CString};
+use std::ops::Deref;
 
+use libduckdb_sys::{
+    duckdb_close, duckdb_connect, duckdb_connection, duckdb_database, duckdb_free, duckdb_open,
+    duckdb_state_DuckDBError,
+};
+use crate::ffi::{duckdb_ext_add_config_option, duckdb_ext_register_macro};
+use crate::replacement_scan::{self, ReplacementScanInfo};
+use crate::{Connection, Error, LogicalType, Result, Value};
+
+#[derive(Debug, Clone, Copy)]
 pub struct Database {
     ptr: duckdb_database,
 }
 
+// The DuckDB database handle is safe to share between threads, each thread
+// opening its own [Connection].
+unsafe impl Send for Database {}
+unsafe impl Sync for Database {}
+
 impl From<duckdb_database> for Database {
     fn from(ptr: duckdb_database) -> Self {
         Self { ptr }
@@ -26,6 +40,21 @@ impl From<duckdb_database> for Database {
 }
 
 impl Database {
+    /// Open a new in-memory database, such as for tests.
+    ///
+    /// A [Database] doesn't own what it points to, so the database stays open
+    /// until the process exits.
+    pub fn open_in_memory() -> Result<Self> {
+        let mut ptr: duckdb_database = std::ptr::null_mut();
+
+        let state = unsafe { duckdb_open(std::ptr::null(), &mut ptr) };
+        if state == duckdb_state_DuckDBError {
+            return Err(Error::DuckDB("could not open database".to_string()));
+        }
+
+        Ok(Self { ptr })
+    }
+
     pub fn connect(&self) -> Result<Connection> {
         let mut connection: duckdb_connection = std::ptr::null_mut();
 
@@ -36,4 +65,91 @@ impl Database {
 
         Ok(Connection::from(connection))
     }
+
+    /// Register an option that can be changed with `SET name = value` and read
+    /// back with [Connection::config_option] or [BindInfo::config_option].
+    ///
+    /// [BindInfo::config_option]: crate::table_function::BindInfo::config_option
+    pub fn add_config_option(
+        &self,
+        name: &str,
+        description: &str,
+        logical_type: &LogicalType,
+        default_value: Value,
+    ) -> Result<()> {
+        let c_name = CString::new(name).map_err(|e| Error::DuckDB(e.to_string()))?;
+        let c_description = CString::new(description).map_err(|e| Error::DuckDB(e.to_string()))?;
+        let added = unsafe {
+            duckdb_ext_add_config_option(
+                self.ptr,
+                c_name.as_ptr(),
+                c_description.as_ptr(),
+                logical_type.ptr,
+                default_value.ptr,
+            )
+        };
+        if !added {
+            return Err(Error::DuckDB(format!("could not add option {name}")));
+        }
+        Ok(())
+    }
+
+    /// Register the macro defined by `sql`, a `CREATE MACRO` statement, for all
+    /// connections to the database.
+    ///
+    /// It is kept in the system catalog, so it isn't stored in the database file,
+    /// can be registered in databases opened read-only, and is hidden by any
+    /// macro of the same name users create themselves.
+    pub fn register_macro(&self, sql: &str) -> Result<()> {
+        let c_sql = CString::new(sql).map_err(|e| Error::DuckDB(e.to_string()))?;
+        let error = unsafe { duckdb_ext_register_macro(self.ptr, c_sql.as_ptr()) };
+        if error.is_null() {
+            return Ok(());
+        }
+        let message = unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned();
+        unsafe { duckdb_free(error.cast()) };
+        Err(Error::DuckDB(message))
+    }
+
+    /// Register a replacement scan, which DuckDB calls with every table name it
+    /// cannot find so that `func` can replace it with a table function call.
+    pub fn add_replacement_scan<F>(&self, func: F)
+    where
+        F: Fn(&ReplacementScanInfo, &str) -> Result<()> + Send + Sync + 'static,
+    {
+        replacement_scan::add_replacement_scan(self.ptr, func)
+    }
+}
+
+/// A handle on a database that keeps it open until the handle is dropped,
+/// unlike [Database], such as the one a table function is bound in.
+#[derive(Debug)]
+pub struct SharedDatabase {
+    database: Database,
+}
+
+impl SharedDatabase {
+    /// Take ownership of a handle that is to be closed with `duckdb_close`.
+    pub(crate) fn from_owned(ptr: duckdb_database) -> Self {
+        Self {
+            database: Database::from(ptr),
+        }
+    }
+}
+
+impl Deref for SharedDatabase {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.database
+    }
+}
+
+impl Drop for SharedDatabase {
+    fn drop(&mut self) {
+        // Only closes the handle, the database stays open as long as others use it
+        unsafe { duckdb_close(&mut self.database.ptr) };
+    }
 }
@@ -14,9 +14,19 @@
 
 #include "duckdb_athena_rust.h"
 
+#include <cstring>
+#include <mutex>
 #include <string>
+#include <unordered_map>
 
 #include "duckdb.hpp"
+#include "duckdb/common/vector_operations/vector_operations.hpp"
+#include "duckdb/function/table_function.hpp"
+#include "duckdb/main/capi/capi_internal.hpp"
+#include "duckdb/main/extension_util.hpp"
+#include "duckdb/parser/parsed_data/create_macro_info.hpp"
+#include "duckdb/parser/parser.hpp"
+#include "duckdb/parser/statement/create_statement.hpp"
 
 namespace
 {
@@ -31,6 +41,99 @@ namespace
     return members;
   }
 
+  const duckdb::Value &unwrap_value(duckdb_value value)
+  {
+    return *reinterpret_cast<duckdb::Value *>(value);
+  }
+
+  // The message of an exception DuckDB threw, for the caller to free with duckdb_free.
+  char *error_message(std::exception &e)
+  {
+    duckdb::ErrorData error(e);
+    auto &message = error.Message();
+    auto result = static_cast<char *>(duckdb_malloc(message.size() + 1));
+    std::memcpy(result, message.c_str(), message.size() + 1);
+    return result;
+  }
+
+  // DuckDB version whose private C API structs are mirrored below. The C API
+  // has no accessors for what they hold, and the mirrors are only read when the
+  // loaded DuckDB is this version. The duckdb crates are pinned to it as well.
+  //
+  // The originals are defined in table_function-c.cpp rather than a header, so
+  // their layout can't be checked at compile time. This version check and the
+  // `=1.1.1` pin are all that keep the mirrors in line with them: bumping DuckDB
+  // means comparing the mirrors with its sources again.
+  constexpr const char *MIRRORED_VERSION = "v1.1.1";
+
+  bool mirrors_supported()
+  {
+    static const bool supported = std::strcmp(duckdb_library_version(), MIRRORED_VERSION) == 0;
+    return supported;
+  }
+
+  // Leading member of the C API's CTableInternalBindInfo (table_function-c.cpp),
+  // which duckdb_bind_info points to.
+  struct BindInfoContext
+  {
+    duckdb::ClientContext &context;
+  };
+
+  // The C API's CTableBindData and CTableGlobalInitData (table_function-c.cpp),
+  // which hold the data the bind and init functions of a table function set.
+  // References are mirrored as pointers.
+  struct TableBindData : public duckdb::TableFunctionData
+  {
+    duckdb::TableFunctionInfo *info;
+    void *bind_data;
+    duckdb_delete_callback_t delete_callback;
+    duckdb::unique_ptr<duckdb::NodeStatistics> stats;
+  };
+
+  struct TableGlobalInitData : public duckdb::GlobalTableFunctionState
+  {
+    void *init_data;
+    duckdb_delete_callback_t delete_callback;
+    idx_t max_threads;
+  };
+
+  // Progress functions set with duckdb_ext_table_function_set_progress, by the
+  // info of the table function they belong to, which its bind data points to.
+  std::mutex progress_lock;
+  std::unordered_map<const duckdb::TableFunctionInfo *, duckdb_ext_table_function_progress_t> progress_functions;
+
+  double table_function_progress(duckdb::ClientContext &, const duckdb::FunctionData *bind_data,
+                                 const duckdb::GlobalTableFunctionState *global_state)
+  {
+    if (!bind_data || !global_state)
+    {
+      return -1;
+    }
+    auto &bind = *static_cast<const TableBindData *>(bind_data);
+    auto &init = *static_cast<const TableGlobalInitData *>(global_state);
+    duckdb_ext_table_function_progress_t progress;
+    {
+      std::lock_guard<std::mutex> guard(progress_lock);
+      auto entry = progress_functions.find(bind.info);
+      if (entry == progress_functions.end())
+      {
+        return -1;
+      }
+      progress = entry->second;
+    }
+    return progress(bind.bind_data, init.init_data);
+  }
+
+  duckdb_value current_setting(duckdb::ClientContext &context, const char *name)
+  {
+    duckdb::Value result;
+    if (!context.TryGetCurrentSetting(name, result))
+    {
+      return nullptr;
+    }
+    return reinterpret_cast<duckdb_value>(new duckdb::Value(result));
+  }
+
 } // namespace
 
 extern "C"
@@ -44,4 +147,161 @@ extern "C"
     return reinterpret_cast<duckdb_logical_type>(stype);
     ;
   }
+
+  char *duckdb_ext_data_chunk_cast_into(duckdb_data_chunk source, duckdb_data_chunk target)
+  {
+    auto &src = *reinterpret_cast<duckdb::DataChunk *>(source);
+    auto &dst = *reinterpret_cast<duckdb::DataChunk *>(target);
+    try
+    {
+      for (idx_t i = 0; i < src.ColumnCount(); i++)
+      {
+        if (src.data[i].GetType() == dst.data[i].GetType())
+        {
+          dst.data[i].Reference(src.data[i]);
+        }
+        else if (dst.data[i].GetType().id() == duckdb::LogicalTypeId::ENUM)
+        {
+          // Values missing from the dictionary become NULL
+          std::string error;
+          duckdb::VectorOperations::DefaultTryCast(src.data[i], dst.data[i], src.size(), &error);
+        }
+        else
+        {
+          duckdb::VectorOperations::DefaultCast(src.data[i], dst.data[i], src.size());
+        }
+      }
+      dst.SetCardinality(src.size());
+      return nullptr;
+    }
+    catch (std::exception &e)
+    {
+      return error_message(e);
+    }
+  }
+
+  duckdb_value duckdb_ext_data_chunk_get_value(duckdb_data_chunk chunk, idx_t column, idx_t row)
+  {
+    auto &data = *reinterpret_cast<duckdb::DataChunk *>(chunk);
+    return reinterpret_cast<duckdb_value>(new duckdb::Value(data.GetValue(column, row)));
+  }
+
+  bool duckdb_ext_is_null_value(duckdb_value value)
+  {
+    return unwrap_value(value).IsNull();
+  }
+
+  idx_t duckdb_ext_get_list_size(duckdb_value value)
+  {
+    auto &val = unwrap_value(value);
+    if (val.IsNull() || val.type().id() != duckdb::LogicalTypeId::LIST)
+    {
+      return 0;
+    }
+    return duckdb::ListValue::GetChildren(val).size();
+  }
+
+  duckdb_value duckdb_ext_get_list_child(duckdb_value value, idx_t index)
+  {
+    auto &children = duckdb::ListValue::GetChildren(unwrap_value(value));
+    return reinterpret_cast<duckdb_value>(new duckdb::Value(children[index]));
+  }
+
+  duckdb_value duckdb_ext_get_struct_child(duckdb_value value, idx_t index)
+  {
+    auto &children = duckdb::StructValue::GetChildren(unwrap_value(value));
+    return reinterpret_cast<duckdb_value>(new duckdb::Value(children[index]));
+  }
+
+  bool duckdb_ext_add_config_option(duckdb_database database, const char *name, const char *description,
+                                    duckdb_logical_type type, duckdb_value default_value)
+  {
+    try
+    {
+      auto &db = *reinterpret_cast<duckdb::DatabaseData *>(database)->database;
+      auto &config = duckdb::DBConfig::GetConfig(*db.instance);
+      config.AddExtensionOption(name, description, *reinterpret_cast<duckdb::LogicalType *>(type),
+                                unwrap_value(default_value));
+      return true;
+    }
+    catch (...)
+    {
+      return false;
+    }
+  }
+
+  duckdb_value duckdb_ext_get_config_option(duckdb_connection connection, const char *name)
+  {
+    return current_setting(*reinterpret_cast<duckdb::Connection *>(connection)->context, name);
+  }
+
+  duckdb_value duckdb_ext_bind_get_config_option(duckdb_bind_info info, const char *name)
+  {
+    if (!mirrors_supported())
+    {
+      return nullptr;
+    }
+    return current_setting(reinterpret_cast<BindInfoContext *>(info)->context, name);
+  }
+
+  duckdb_database duckdb_ext_bind_get_database(duckdb_bind_info info)
+  {
+    if (!mirrors_supported())
+    {
+      return nullptr;
+    }
+    auto &context = reinterpret_cast<BindInfoContext *>(info)->context;
+    auto wrapper = new duckdb::DatabaseData();
+    wrapper->database = duckdb::make_uniq<duckdb::DuckDB>(*context.db);
+    return reinterpret_cast<duckdb_database>(wrapper);
+  }
+
+  char *duckdb_ext_register_macro(duckdb_database database, const char *sql)
+  {
+    try
+    {
+      auto &db = *reinterpret_cast<duckdb::DatabaseData *>(database)->database;
+      duckdb::Parser parser;
+      parser.ParseQuery(sql);
+      if (parser.statements.size() != 1 || parser.statements[0]->type != duckdb::StatementType::CREATE_STATEMENT)
+      {
+        throw duckdb::InvalidInputException("expected a single CREATE MACRO statement");
+      }
+      auto &info = *parser.statements[0]->Cast<duckdb::CreateStatement>().info;
+      if (info.type != duckdb::CatalogType::MACRO_ENTRY && info.type != duckdb::CatalogType::TABLE_MACRO_ENTRY)
+      {
+        throw duckdb::InvalidInputException("expected a single CREATE MACRO statement");
+      }
+      auto &macro = info.Cast<duckdb::CreateMacroInfo>();
+      // The system catalog is searched after the user's own schemas and is not
+      // stored in the database file
+      macro.catalog = duckdb::SYSTEM_CATALOG;
+      macro.schema = duckdb::DEFAULT_SCHEMA;
+      macro.temporary = false;
+      macro.internal = true;
+      macro.on_conflict = duckdb::OnCreateConflict::REPLACE_ON_CONFLICT;
+      duckdb::ExtensionUtil::RegisterFunction(*db.instance, macro);
+      return nullptr;
+    }
+    catch (std::exception &e)
+    {
+      return error_message(e);
+    }
+  }
+
+  bool duckdb_ext_table_function_set_progress(duckdb_table_function function,
+                                              duckdb_ext_table_function_progress_t progress)
+  {
+    if (!mirrors_supported())
+    {
+      return false;
+    }
+    auto &table_function = *reinterpret_cast<duckdb::TableFunction *>(function);
+    {
+      std::lock_guard<std::mutex> guard(progress_lock);
+      progress_functions[table_function.function_info.get()] = progress;
+    }
+    table_function.table_scan_progress = table_function_progress;
+    return true;
+  }
 }
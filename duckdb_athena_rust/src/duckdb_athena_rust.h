@@ -18,4 +18,33 @@
 extern "C" {
     DUCKDB_EXTENSION_API duckdb_logical_type duckdb_create_struct_type(
         duckdb_logical_type *member_types, const char **member_names, idx_t member_count);
+
+    // Returns the error message, to be freed with duckdb_free, if a cast failed.
+    DUCKDB_EXTENSION_API char *duckdb_ext_data_chunk_cast_into(
+        duckdb_data_chunk source, duckdb_data_chunk target);
+
+    DUCKDB_EXTENSION_API duckdb_value duckdb_ext_data_chunk_get_value(
+        duckdb_data_chunk chunk, idx_t column, idx_t row);
+
+    DUCKDB_EXTENSION_API bool duckdb_ext_is_null_value(duckdb_value value);
+    DUCKDB_EXTENSION_API idx_t duckdb_ext_get_list_size(duckdb_value value);
+    DUCKDB_EXTENSION_API duckdb_value duckdb_ext_get_list_child(duckdb_value value, idx_t index);
+    DUCKDB_EXTENSION_API duckdb_value duckdb_ext_get_struct_child(duckdb_value value, idx_t index);
+
+    DUCKDB_EXTENSION_API bool duckdb_ext_add_config_option(duckdb_database database, const char *name,
+        const char *description, duckdb_logical_type type, duckdb_value default_value);
+    DUCKDB_EXTENSION_API duckdb_value duckdb_ext_get_config_option(duckdb_connection connection, const char *name);
+    DUCKDB_EXTENSION_API duckdb_value duckdb_ext_bind_get_config_option(duckdb_bind_info info, const char *name);
+    // Returns a new handle, to be closed with duckdb_close, on the database a
+    // table function is bound in, keeping it open until then.
+    DUCKDB_EXTENSION_API duckdb_database duckdb_ext_bind_get_database(duckdb_bind_info info);
+
+    // Registers the macro a CREATE MACRO statement defines in the system catalog,
+    // behind any of the same name users create. Returns the error message, to be
+    // freed with duckdb_free, if it failed.
+    DUCKDB_EXTENSION_API char *duckdb_ext_register_macro(duckdb_database database, const char *sql);
+
+    typedef double (*duckdb_ext_table_function_progress_t)(void *bind_data, void *init_data);
+    DUCKDB_EXTENSION_API bool duckdb_ext_table_function_set_progress(
+        duckdb_table_function function, duckdb_ext_table_function_progress_t progress);
 };
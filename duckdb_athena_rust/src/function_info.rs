@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use libduckdb_sys::{duckdb_function_get_init_data, duckdb_function_get_bind_data, duckdb_function_info, duckdb_function_set_error};
+use libduckdb_sys::{duckdb_function_get_init_data, duckdb_function_get_bind_data, duckdb_function_get_local_init_data, duckdb_function_info, duckdb_function_set_error};
 use crate::Error;
 
 /// UDF
@@ -27,12 +27,33 @@ impl From<duckdb_function_info> for FunctionInfo {
 }
 
 impl FunctionInfo {
-    pub fn bind_data<T>(&self) -> *mut T {
-        unsafe { duckdb_function_get_bind_data(self.ptr).cast() }
+    /// The data set by the bind function.
+    ///
+    /// # Safety
+    /// `T` must be the type the bind data was set with.
+    pub unsafe fn bind_data<T>(&self) -> &T {
+        let ptr = duckdb_function_get_bind_data(self.ptr).cast::<T>();
+        ptr.as_ref().expect("bind data is not set")
     }
 
-    pub fn init_data<T>(&self) -> *mut T {
-        unsafe { duckdb_function_get_init_data(self.ptr).cast() }
+    /// The data set by the (global) init function.
+    ///
+    /// # Safety
+    /// `T` must be the type the init data was set with. It is shared by all
+    /// threads scanning in parallel, so any mutable state needs to be synchronized.
+    pub unsafe fn init_data<T>(&self) -> &T {
+        let ptr = duckdb_function_get_init_data(self.ptr).cast::<T>();
+        ptr.as_ref().expect("init data is not set")
+    }
+
+    /// The data set by the local init function for the calling thread, if any.
+    ///
+    /// # Safety
+    /// `T` must be the type the local init data was set with.
+    pub unsafe fn local_init_data<T>(&self) -> Option<&mut T> {
+        duckdb_function_get_local_init_data(self.ptr)
+            .cast::<T>()
+            .as_mut()
     }
 
     pub fn set_error(&self, error: Error) {
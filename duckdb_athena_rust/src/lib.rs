@@ -12,25 +12,36 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod appender;
 mod connection;
 mod data_chunk;
 mod database;
 mod error;
 mod function_info;
 mod logical_type;
+mod prepared_statement;
+mod query_result;
+pub mod replacement_scan;
+pub mod scalar_function;
 pub mod table_function;
+pub mod vtab;
 mod value;
 mod vector;
 
+pub use appender::Appender;
 pub use connection::Connection;
 pub use data_chunk::DataChunk;
-pub use database::Database;
+pub use database::{Database, SharedDatabase};
 pub use error::{Error, Result};
 pub use function_info::FunctionInfo;
 pub use logical_type::{LogicalType, LogicalTypeId};
+pub use prepared_statement::PreparedStatement;
+pub use query_result::QueryResult;
 pub use value::Value;
-pub use vector::{FlatVector, Inserter, ListVector, StructVector, Vector};
-use std::mem::size_of;
+pub use vector::{
+    Date, Decimal, EnumIndex, FlatVector, Inserter, ListVector, MapVector, StructVector, Timestamp,
+    Vector, VectorValue,
+};
 
 pub use libduckdb_sys::{duckdb_vector_size, duckdb_bind_info, duckdb_data_chunk, duckdb_free, duckdb_function_info, duckdb_init_info, _duckdb_database, duckdb_library_version};
 
@@ -42,10 +53,51 @@ pub mod ffi {
     #![allow(unused)]
     #![allow(improper_ctypes)]
     #![allow(clippy::upper_case_acronyms)]
-}
 
-/// # Safety
-/// This function is obviously unsafe
-pub unsafe fn malloc_struct<T>() -> *mut T {
-    libduckdb_sys::duckdb_malloc(size_of::<T>()).cast::<T>()
+    use std::ffi::{c_char, c_void};
+
+    use libduckdb_sys::{
+        duckdb_bind_info, duckdb_connection, duckdb_data_chunk, duckdb_database,
+        duckdb_logical_type, duckdb_table_function, duckdb_value, idx_t,
+    };
+
+    /// Percentage of a scan done, from its bind and init data, negative if unknown.
+    pub type duckdb_ext_table_function_progress_t =
+        Option<unsafe extern "C" fn(bind_data: *mut c_void, init_data: *mut c_void) -> f64>;
+
+    // Helpers implemented in duckdb_athena_rust.cc on top of the DuckDB C++ API.
+    extern "C" {
+        pub fn duckdb_ext_data_chunk_cast_into(
+            source: duckdb_data_chunk,
+            target: duckdb_data_chunk,
+        ) -> *mut c_char;
+        pub fn duckdb_ext_data_chunk_get_value(
+            chunk: duckdb_data_chunk,
+            column: idx_t,
+            row: idx_t,
+        ) -> duckdb_value;
+        pub fn duckdb_ext_is_null_value(value: duckdb_value) -> bool;
+        pub fn duckdb_ext_get_list_size(value: duckdb_value) -> idx_t;
+        pub fn duckdb_ext_get_list_child(value: duckdb_value, index: idx_t) -> duckdb_value;
+        pub fn duckdb_ext_get_struct_child(value: duckdb_value, index: idx_t) -> duckdb_value;
+        pub fn duckdb_ext_add_config_option(
+            database: duckdb_database,
+            name: *const c_char,
+            description: *const c_char,
+            type_: duckdb_logical_type,
+            default_value: duckdb_value,
+        ) -> bool;
+        pub fn duckdb_ext_get_config_option(
+            connection: duckdb_connection,
+            name: *const c_char,
+        ) -> duckdb_value;
+        pub fn duckdb_ext_bind_get_config_option(
+            info: duckdb_bind_info,
+            name: *const c_char,
+        ) -> duckdb_value;
+        pub fn duckdb_ext_table_function_set_progress(
+            function: duckdb_table_function,
+            progress: duckdb_ext_table_function_progress_t,
+        ) -> bool;
+    }
 }
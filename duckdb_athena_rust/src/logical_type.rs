@@ -17,8 +17,10 @@ use std::fmt::Debug;
 
 use libduckdb_sys::*;
 
+use crate::value::take_c_string;
+
 #[repr(u32)]
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogicalTypeId {
     Boolean = DUCKDB_TYPE_DUCKDB_TYPE_BOOLEAN,
     Tinyint = DUCKDB_TYPE_DUCKDB_TYPE_TINYINT,
@@ -107,6 +109,14 @@ impl Debug for LogicalType {
                 }
                 write!(f, ">")
             }
+            LogicalTypeId::Map => {
+                let (key_type, value_type) = (self.map_key_type(), self.map_value_type());
+                write!(f, "map<{key_type:?}, {value_type:?}>")
+            }
+            LogicalTypeId::Decimal => {
+                let (width, scale) = (self.decimal_width(), self.decimal_scale());
+                write!(f, "decimal({width}, {scale})")
+            }
             _ => write!(f, "{:?}", self.id()),
         }
     }
@@ -172,6 +182,88 @@ impl LogicalType {
         }
     }
 
+    /// Make a `MAP` type from its key and value types.
+    pub fn map_type(key_type: &LogicalType, value_type: &LogicalType) -> Self {
+        unsafe {
+            Self {
+                ptr: duckdb_create_map_type(key_type.ptr, value_type.ptr),
+            }
+        }
+    }
+
+    /// Make a `UNION` type from its member names and types.
+    pub fn union_type(members: &[(&str, LogicalType)]) -> Self {
+        let names: Vec<CString> = members.iter().map(|m| CString::new(m.0).unwrap()).collect();
+        let mut types: Vec<duckdb_logical_type> = members.iter().map(|m| m.1.ptr).collect();
+        let mut name_ptrs: Vec<*const c_char> = names.iter().map(|n| n.as_ptr()).collect();
+
+        unsafe {
+            Self {
+                ptr: duckdb_create_union_type(
+                    types.as_mut_ptr(),
+                    name_ptrs.as_mut_ptr(),
+                    members.len() as idx_t,
+                ),
+            }
+        }
+    }
+
+    /// Key type of a `MAP` type.
+    pub fn map_key_type(&self) -> Self {
+        assert_eq!(self.id(), LogicalTypeId::Map);
+        Self::from(unsafe { duckdb_map_type_key_type(self.ptr) })
+    }
+
+    /// Value type of a `MAP` type.
+    pub fn map_value_type(&self) -> Self {
+        assert_eq!(self.id(), LogicalTypeId::Map);
+        Self::from(unsafe { duckdb_map_type_value_type(self.ptr) })
+    }
+
+    /// Make an `ENUM` type whose dictionary holds `values`, in order.
+    pub fn enum_type(values: &[&str]) -> Self {
+        let names: Vec<CString> = values.iter().map(|v| CString::new(*v).unwrap()).collect();
+        let mut name_ptrs: Vec<*const c_char> = names.iter().map(|n| n.as_ptr()).collect();
+
+        unsafe {
+            Self {
+                ptr: duckdb_create_enum_type(name_ptrs.as_mut_ptr(), values.len() as idx_t),
+            }
+        }
+    }
+
+    /// The dictionary of an `ENUM` type.
+    pub fn enum_values(&self) -> Vec<String> {
+        assert_eq!(self.id(), LogicalTypeId::Enum);
+        unsafe {
+            let size = duckdb_enum_dictionary_size(self.ptr);
+            (0..size)
+                .map(|idx| take_c_string(duckdb_enum_dictionary_value(self.ptr, idx as idx_t)))
+                .collect()
+        }
+    }
+
+    /// Make a `DECIMAL(width, scale)` type.
+    pub fn decimal(width: u8, scale: u8) -> Self {
+        unsafe {
+            Self {
+                ptr: duckdb_create_decimal_type(width, scale),
+            }
+        }
+    }
+
+    /// Total number of digits of a `DECIMAL` type.
+    pub fn decimal_width(&self) -> u8 {
+        assert_eq!(self.id(), LogicalTypeId::Decimal);
+        unsafe { duckdb_decimal_width(self.ptr) }
+    }
+
+    /// Number of digits after the decimal point of a `DECIMAL` type.
+    pub fn decimal_scale(&self) -> u8 {
+        assert_eq!(self.id(), LogicalTypeId::Decimal);
+        unsafe { duckdb_decimal_scale(self.ptr) }
+    }
+
     /// Logical type ID
     pub fn id(&self) -> LogicalTypeId {
         let duckdb_type_id = unsafe { duckdb_get_type_id(self.ptr) };
@@ -182,6 +274,8 @@ impl LogicalType {
         match self.id() {
             LogicalTypeId::Struct => unsafe { duckdb_struct_type_child_count(self.ptr) as usize },
             LogicalTypeId::List => 1,
+            LogicalTypeId::Map => 2,
+            LogicalTypeId::Union => unsafe { duckdb_union_type_member_count(self.ptr) as usize },
             _ => 0,
         }
     }
@@ -0,0 +1,108 @@
+// Copyright 2023 Lance Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ffi::CStr;
+
+use libduckdb_sys::{
+    duckdb_bind_value, duckdb_destroy_pending, duckdb_destroy_prepare, duckdb_destroy_result,
+    duckdb_execute_pending, duckdb_execute_prepared, duckdb_nparams, duckdb_pending_error,
+    duckdb_pending_prepared_streaming, duckdb_pending_result, duckdb_prepared_statement,
+    duckdb_result, duckdb_result_error, duckdb_state_DuckDBError,
+};
+use crate::{Error, QueryResult, Result, Value};
+
+/// A SQL statement prepared through [crate::Connection::prepare], which can be
+/// executed repeatedly with different parameters.
+#[derive(Debug)]
+pub struct PreparedStatement {
+    ptr: duckdb_prepared_statement,
+}
+
+impl From<duckdb_prepared_statement> for PreparedStatement {
+    fn from(ptr: duckdb_prepared_statement) -> Self {
+        Self { ptr }
+    }
+}
+
+impl Drop for PreparedStatement {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe {
+                duckdb_destroy_prepare(&mut self.ptr);
+            }
+        }
+        self.ptr = std::ptr::null_mut();
+    }
+}
+
+impl PreparedStatement {
+    /// The number of `?` parameters of the statement.
+    pub fn num_parameters(&self) -> usize {
+        unsafe { duckdb_nparams(self.ptr) as usize }
+    }
+
+    /// Bind `value` to the parameter at `index`, counting from 1.
+    pub fn bind(&self, index: usize, value: &Value) -> Result<()> {
+        let state = unsafe { duckdb_bind_value(self.ptr, index as u64, value.ptr) };
+        if state == duckdb_state_DuckDBError {
+            return Err(Error::DuckDB(format!("could not bind parameter {index}")));
+        }
+        Ok(())
+    }
+
+    /// Execute the statement with the parameters bound so far.
+    pub fn execute(&self) -> Result<QueryResult> {
+        let mut result: duckdb_result = unsafe { std::mem::zeroed() };
+
+        let state = unsafe { duckdb_execute_prepared(self.ptr, &mut result) };
+        if state == duckdb_state_DuckDBError {
+            let message = unsafe { CStr::from_ptr(duckdb_result_error(&mut result)) }
+                .to_string_lossy()
+                .into_owned();
+            unsafe { duckdb_destroy_result(&mut result) };
+            return Err(Error::DuckDB(message));
+        }
+
+        Ok(QueryResult::from(result))
+    }
+
+    /// Execute the statement with the parameters bound so far, producing its
+    /// rows as [QueryResult::fetch_chunk] asks for them rather than all at once.
+    ///
+    /// The connection can't run other queries until the result is dropped.
+    pub fn execute_streaming(&self) -> Result<QueryResult> {
+        let mut pending: duckdb_pending_result = std::ptr::null_mut();
+        let state = unsafe { duckdb_pending_prepared_streaming(self.ptr, &mut pending) };
+        if state == duckdb_state_DuckDBError {
+            let message = unsafe { CStr::from_ptr(duckdb_pending_error(pending)) }
+                .to_string_lossy()
+                .into_owned();
+            unsafe { duckdb_destroy_pending(&mut pending) };
+            return Err(Error::DuckDB(message));
+        }
+
+        let mut result: duckdb_result = unsafe { std::mem::zeroed() };
+        let state = unsafe { duckdb_execute_pending(pending, &mut result) };
+        unsafe { duckdb_destroy_pending(&mut pending) };
+        if state == duckdb_state_DuckDBError {
+            let message = unsafe { CStr::from_ptr(duckdb_result_error(&mut result)) }
+                .to_string_lossy()
+                .into_owned();
+            unsafe { duckdb_destroy_result(&mut result) };
+            return Err(Error::DuckDB(message));
+        }
+
+        Ok(QueryResult::from(result))
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright 2023 Lance Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ffi::CStr;
+
+use libduckdb_sys::{
+    duckdb_column_count, duckdb_column_logical_type, duckdb_column_name, duckdb_destroy_result,
+    duckdb_fetch_chunk, duckdb_result, duckdb_result_error,
+};
+
+use crate::{DataChunk, Error, LogicalType, Result, Value};
+
+/// The result of a query run through [crate::Connection::query] or a
+/// [crate::PreparedStatement].
+pub struct QueryResult {
+    result: duckdb_result,
+}
+
+// A result can be read from any thread, though by one at a time.
+unsafe impl Send for QueryResult {}
+
+impl From<duckdb_result> for QueryResult {
+    fn from(result: duckdb_result) -> Self {
+        Self { result }
+    }
+}
+
+impl Drop for QueryResult {
+    fn drop(&mut self) {
+        unsafe { duckdb_destroy_result(&mut self.result) }
+    }
+}
+
+impl QueryResult {
+    /// The C API takes the result mutably even where it only reads it.
+    fn result_ptr(&mut self) -> *mut duckdb_result {
+        &mut self.result
+    }
+
+    pub fn num_columns(&mut self) -> usize {
+        unsafe { duckdb_column_count(self.result_ptr()) as usize }
+    }
+
+    /// The name of the column at `idx`.
+    pub fn column_name(&mut self, idx: usize) -> String {
+        assert!(idx < self.num_columns());
+        // The name is owned by the result.
+        let name = unsafe { duckdb_column_name(self.result_ptr(), idx as u64) };
+        unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// The type of the column at `idx`.
+    pub fn column_type(&mut self, idx: usize) -> LogicalType {
+        assert!(idx < self.num_columns());
+        LogicalType::from(unsafe { duckdb_column_logical_type(self.result_ptr(), idx as u64) })
+    }
+
+    /// Read all remaining rows of the result, one [Value] per column.
+    pub fn rows(&mut self) -> Vec<Vec<Value>> {
+        let mut rows = Vec::new();
+        while let Some(chunk) = self.fetch_chunk() {
+            for row in 0..chunk.len() {
+                rows.push(
+                    (0..chunk.num_columns())
+                        .map(|idx| chunk.value(idx, row))
+                        .collect(),
+                );
+            }
+        }
+        rows
+    }
+
+    /// Fetch the next chunk of the result, or `None` once all rows were read.
+    pub fn fetch_chunk(&mut self) -> Option<DataChunk> {
+        let ptr = unsafe { duckdb_fetch_chunk(self.result) };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(DataChunk::from_owned(ptr))
+        }
+    }
+
+    /// Fetch the next chunk like [Self::fetch_chunk], but fail with the error a
+    /// streaming result ran into while producing it instead of ending early.
+    pub fn try_fetch_chunk(&mut self) -> Result<Option<DataChunk>> {
+        if let Some(chunk) = self.fetch_chunk() {
+            return Ok(Some(chunk));
+        }
+        let error = unsafe { duckdb_result_error(self.result_ptr()) };
+        if error.is_null() {
+            return Ok(None);
+        }
+        let message = unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned();
+        Err(Error::DuckDB(message))
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2023 Lance Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+
+use libduckdb_sys::{
+    duckdb_add_replacement_scan, duckdb_database, duckdb_replacement_scan_add_parameter,
+    duckdb_replacement_scan_info, duckdb_replacement_scan_set_error,
+    duckdb_replacement_scan_set_function_name,
+};
+use crate::{Error, Result, Value};
+
+/// The Rust closure behind a replacement scan.
+///
+/// It receives every table name DuckDB could not find and replaces it with a
+/// table function call through [ReplacementScanInfo::set_function_name], or
+/// leaves it alone by doing nothing.
+type ReplacementClosure = Box<dyn Fn(&ReplacementScanInfo, &str) -> Result<()> + Send + Sync>;
+
+/// DuckDB ReplacementScanInfo, the table function call a table name is replaced with.
+pub struct ReplacementScanInfo {
+    ptr: duckdb_replacement_scan_info,
+}
+
+impl From<duckdb_replacement_scan_info> for ReplacementScanInfo {
+    fn from(ptr: duckdb_replacement_scan_info) -> Self {
+        Self { ptr }
+    }
+}
+
+impl ReplacementScanInfo {
+    /// Replace the table with a call to the table function `name`.
+    pub fn set_function_name(&self, name: &str) {
+        let c_string = CString::new(name).unwrap();
+        unsafe {
+            duckdb_replacement_scan_set_function_name(self.ptr, c_string.as_ptr());
+        }
+    }
+
+    /// Add a positional parameter to the table function call.
+    pub fn add_parameter(&self, value: &Value) {
+        unsafe {
+            duckdb_replacement_scan_add_parameter(self.ptr, value.ptr);
+        }
+    }
+
+    pub fn set_error(&self, error: Error) {
+        unsafe {
+            duckdb_replacement_scan_set_error(self.ptr, error.c_str().as_ptr());
+        }
+    }
+}
+
+/// Register `func` as a replacement scan of the database `db`.
+///
+/// An error returned by the closure fails the query with its message.
+pub(crate) fn add_replacement_scan<F>(db: duckdb_database, func: F)
+where
+    F: Fn(&ReplacementScanInfo, &str) -> Result<()> + Send + Sync + 'static,
+{
+    let closure: Box<ReplacementClosure> = Box::new(Box::new(func));
+    unsafe {
+        duckdb_add_replacement_scan(
+            db,
+            Some(call_replacement_closure),
+            Box::into_raw(closure).cast(),
+            Some(drop_replacement_closure),
+        );
+    }
+}
+
+unsafe extern "C" fn call_replacement_closure(
+    info: duckdb_replacement_scan_info,
+    table_name: *const c_char,
+    data: *mut c_void,
+) {
+    let closure = &*data.cast::<ReplacementClosure>();
+    let info = ReplacementScanInfo::from(info);
+    let table_name = CStr::from_ptr(table_name).to_string_lossy();
+
+    if let Err(e) = closure(&info, &table_name) {
+        info.set_error(e);
+    }
+}
+
+unsafe extern "C" fn drop_replacement_closure(v: *mut c_void) {
+    drop(Box::from_raw(v.cast::<ReplacementClosure>()));
+}
@@ -0,0 +1,119 @@
+// Copyright 2023 Lance Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ffi::{c_void, CString};
+
+use libduckdb_sys::{
+    duckdb_create_scalar_function, duckdb_data_chunk, duckdb_destroy_scalar_function,
+    duckdb_function_info, duckdb_scalar_function, duckdb_scalar_function_add_parameter,
+    duckdb_scalar_function_get_extra_info, duckdb_scalar_function_set_error,
+    duckdb_scalar_function_set_extra_info, duckdb_scalar_function_set_function,
+    duckdb_scalar_function_set_name, duckdb_scalar_function_set_return_type, duckdb_vector,
+};
+use crate::{DataChunk, FlatVector, LogicalType, Result};
+
+/// The Rust closure behind a [ScalarFunction].
+///
+/// It receives the (flattened) input columns and writes one value per input
+/// row into the output vector.
+type ScalarClosure = Box<dyn Fn(&DataChunk, &mut FlatVector) -> Result<()> + Send + Sync>;
+
+/// A function that computes one value per input row.
+#[derive(Debug)]
+pub struct ScalarFunction {
+    pub(crate) ptr: duckdb_scalar_function,
+}
+
+impl Drop for ScalarFunction {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe {
+                duckdb_destroy_scalar_function(&mut self.ptr);
+            }
+        }
+        self.ptr = std::ptr::null_mut();
+    }
+}
+
+impl ScalarFunction {
+    /// Creates a new scalar function without parameters.
+    pub fn new(name: &str) -> Self {
+        let this = Self {
+            ptr: unsafe { duckdb_create_scalar_function() },
+        };
+        this.set_name(name);
+        this
+    }
+
+    pub fn set_name(&self, name: &str) -> &Self {
+        unsafe {
+            let string = CString::new(name).unwrap();
+            duckdb_scalar_function_set_name(self.ptr, string.as_ptr());
+        }
+        self
+    }
+
+    /// Adds a parameter to the scalar function.
+    pub fn add_parameter(&self, logical_type: &LogicalType) -> &Self {
+        unsafe {
+            duckdb_scalar_function_add_parameter(self.ptr, logical_type.ptr);
+        }
+        self
+    }
+
+    /// Sets the return type of the scalar function.
+    pub fn set_return_type(&self, logical_type: &LogicalType) -> &Self {
+        unsafe {
+            duckdb_scalar_function_set_return_type(self.ptr, logical_type.ptr);
+        }
+        self
+    }
+
+    /// Sets the Rust closure that computes the function.
+    ///
+    /// An error returned by the closure fails the query with its message.
+    pub fn set_function<F>(&self, func: F) -> &Self
+    where
+        F: Fn(&DataChunk, &mut FlatVector) -> Result<()> + Send + Sync + 'static,
+    {
+        let closure: Box<ScalarClosure> = Box::new(Box::new(func));
+        unsafe {
+            duckdb_scalar_function_set_extra_info(
+                self.ptr,
+                Box::into_raw(closure).cast(),
+                Some(drop_scalar_closure),
+            );
+            duckdb_scalar_function_set_function(self.ptr, Some(call_scalar_closure));
+        }
+        self
+    }
+}
+
+unsafe extern "C" fn call_scalar_closure(
+    info: duckdb_function_info,
+    input: duckdb_data_chunk,
+    output: duckdb_vector,
+) {
+    let closure = &*duckdb_scalar_function_get_extra_info(info).cast::<ScalarClosure>();
+    let input = DataChunk::from(input);
+    let mut output = FlatVector::from(output);
+
+    if let Err(e) = closure(&input, &mut output) {
+        duckdb_scalar_function_set_error(info, e.c_str().as_ptr());
+    }
+}
+
+unsafe extern "C" fn drop_scalar_closure(v: *mut c_void) {
+    drop(Box::from_raw(v.cast::<ScalarClosure>()));
+}
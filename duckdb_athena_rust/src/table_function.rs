@@ -18,18 +18,31 @@ use libduckdb_sys::{
     duckdb_bind_add_result_column, duckdb_bind_get_parameter, duckdb_bind_get_parameter_count,
     duckdb_bind_get_named_parameter,
     duckdb_bind_info, duckdb_bind_set_bind_data, duckdb_bind_set_cardinality,
-    duckdb_bind_set_error, duckdb_create_table_function, duckdb_delete_callback_t,
+    duckdb_bind_set_error, duckdb_create_table_function,
     duckdb_destroy_table_function, duckdb_init_get_bind_data, duckdb_init_get_column_count,
     duckdb_init_get_column_index, duckdb_init_info, duckdb_init_set_error,
-    duckdb_init_set_init_data, duckdb_table_function, duckdb_table_function_add_named_parameter,
-    duckdb_table_function_add_parameter, duckdb_table_function_bind_t,
-    duckdb_table_function_init_t, duckdb_table_function_set_bind,
+    duckdb_init_set_init_data, duckdb_init_set_max_threads, duckdb_table_function,
+    duckdb_table_function_add_named_parameter, duckdb_table_function_add_parameter,
+    duckdb_table_function_bind_t, duckdb_table_function_init_t, duckdb_table_function_set_bind,
     duckdb_table_function_set_function, duckdb_table_function_set_init,
-    duckdb_table_function_set_name, duckdb_table_function_supports_projection_pushdown,
-    duckdb_table_function_t,
+    duckdb_table_function_set_local_init, duckdb_table_function_set_name,
+    duckdb_table_function_supports_projection_pushdown, duckdb_table_function_t,
+};
+use crate::database::SharedDatabase;
+use crate::ffi::{
+    duckdb_ext_bind_get_config_option, duckdb_ext_bind_get_database,
+    duckdb_ext_table_function_progress_t, duckdb_ext_table_function_set_progress,
 };
 use crate::{Error, LogicalType, Value};
 
+/// Drop a boxed `T` handed to DuckDB as bind or init data.
+///
+/// # Safety
+/// `ptr` must come from `Box::<T>::into_raw`.
+unsafe extern "C" fn drop_boxed<T>(ptr: *mut c_void) {
+    drop(Box::from_raw(ptr.cast::<T>()));
+}
+
 /// DuckDB BindInfo.
 pub struct BindInfo {
     ptr: duckdb_bind_info,
@@ -57,19 +70,11 @@ impl BindInfo {
 
     /// Sets the user-provided bind data in the bind object. This object can be retrieved again during execution.
     ///
-    /// # Arguments
-    ///  * `extra_data`: The bind data object.
-    ///  * `destroy`: The callback that will be called to destroy the bind data (if any)
-    ///
-    /// # Safety
-    ///
-    pub fn set_bind_data(
-        &self,
-        data: *mut c_void,
-        free_function: Option<unsafe extern "C" fn(*mut c_void)>,
-    ) {
+    /// The data is moved into a box that DuckDB drops together with the bind data.
+    pub fn set_bind_data<T: 'static>(&self, data: T) {
+        let ptr = Box::into_raw(Box::new(data));
         unsafe {
-            duckdb_bind_set_bind_data(self.ptr, data, free_function);
+            duckdb_bind_set_bind_data(self.ptr, ptr.cast(), Some(drop_boxed::<T>));
         }
     }
 
@@ -88,9 +93,39 @@ impl BindInfo {
         unsafe { Value::from(duckdb_bind_get_parameter(self.ptr, index as u64)) }
     }
 
-    pub fn named_parameter(&self, name: &str) -> Value {
+    /// Get the named parameter with the given name, `None` if it was not supplied.
+    pub fn named_parameter(&self, name: &str) -> Option<Value> {
         let c_string = CString::new(name).unwrap();
-        unsafe { Value::from(duckdb_bind_get_named_parameter(self.ptr, c_string.as_ptr())) }
+        let ptr = unsafe { duckdb_bind_get_named_parameter(self.ptr, c_string.as_ptr()) };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Value::from(ptr))
+        }
+    }
+
+    /// The value of a configuration option in the binding client's session, `None`
+    /// if there is no such option, or if DuckDB isn't the version the shim was
+    /// written against (see `MIRRORED_VERSION` in duckdb_athena_rust.cc).
+    pub fn config_option(&self, name: &str) -> Option<Value> {
+        let c_string = CString::new(name).unwrap();
+        let ptr = unsafe { duckdb_ext_bind_get_config_option(self.ptr, c_string.as_ptr()) };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Value::from(ptr))
+        }
+    }
+
+    /// The database the function is bound in, for running queries of its own,
+    /// `None` if DuckDB isn't the version the shim was written against.
+    pub fn database(&self) -> Option<SharedDatabase> {
+        let ptr = unsafe { duckdb_ext_bind_get_database(self.ptr) };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(SharedDatabase::from_owned(ptr))
+        }
     }
 
     /// Sets the cardinality estimate for the table function, used for optimization.
@@ -120,15 +155,23 @@ impl From<duckdb_init_info> for InitInfo {
 }
 
 impl InitInfo {
-    /// # Safety
-    pub fn set_init_data(&self, data: *mut c_void, freeer: duckdb_delete_callback_t) {
+    /// Sets the init data, from either the global or the thread-local init function.
+    ///
+    /// The data is moved into a box that DuckDB drops once the scan is done.
+    pub fn set_init_data<T: 'static>(&self, data: T) {
+        let ptr = Box::into_raw(Box::new(data));
         unsafe {
-            duckdb_init_set_init_data(self.ptr, data, freeer);
+            duckdb_init_set_init_data(self.ptr, ptr.cast(), Some(drop_boxed::<T>));
         }
     }
 
-    pub fn bind_data<T>(&self) -> *mut T {
-        unsafe { duckdb_init_get_bind_data(self.ptr).cast() }
+    /// The data set by the bind function.
+    ///
+    /// # Safety
+    /// `T` must be the type the bind data was set with.
+    pub unsafe fn bind_data<T>(&self) -> &T {
+        let ptr = duckdb_init_get_bind_data(self.ptr).cast::<T>();
+        ptr.as_ref().expect("bind data is not set")
     }
 
     /// Report that an error has occurred while calling init.
@@ -139,6 +182,13 @@ impl InitInfo {
         unsafe { duckdb_init_set_error(self.ptr, error.c_str().as_ptr()) }
     }
 
+    /// Sets how many threads can process this table function in parallel (default: 1)
+    ///
+    /// Only meaningful when called from the (global) init function.
+    pub fn set_max_threads(&self, max_threads: usize) {
+        unsafe { duckdb_init_set_max_threads(self.ptr, max_threads as u64) }
+    }
+
     /// Get the total number of columns to be projected.
     pub fn projected_column_ids(&self) -> Vec<usize> {
         let num_columns = unsafe { duckdb_init_get_column_count(self.ptr) as usize };
@@ -229,6 +279,20 @@ impl TableFunction {
         self
     }
 
+    /// Sets the thread-local init function of the table function
+    ///
+    /// It runs once for every thread scanning the function, and the data it sets
+    /// is available through [crate::FunctionInfo::local_init_data].
+    ///
+    /// # Arguments
+    ///  * `function`: The local init function
+    pub fn set_local_init(&self, init_func: duckdb_table_function_init_t) -> &Self {
+        unsafe {
+            duckdb_table_function_set_local_init(self.ptr, init_func);
+        }
+        self
+    }
+
     /// Sets the bind function of the table function
     ///
     /// # Arguments
@@ -239,4 +303,19 @@ impl TableFunction {
         }
         self
     }
+
+    /// Sets the function DuckDB's progress bar asks how far a scan has got.
+    ///
+    /// It is passed the bind and init data, and returns a percentage between 0
+    /// and 100, or a negative number if it doesn't know. DuckDB keeps showing no
+    /// progress if it isn't the version the shim was written against.
+    ///
+    /// # Arguments
+    ///  * `progress`: The progress function
+    pub fn set_progress(&self, progress: duckdb_ext_table_function_progress_t) -> &Self {
+        unsafe {
+            duckdb_ext_table_function_set_progress(self.ptr, progress);
+        }
+        self
+    }
 }
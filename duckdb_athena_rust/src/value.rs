@@ -12,8 +12,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use libduckdb_sys::{duckdb_destroy_value, duckdb_get_varchar, duckdb_value};
-use std::ffi::CString;
+use libduckdb_sys::{
+    duckdb_create_bool, duckdb_create_double, duckdb_create_int32, duckdb_create_int64,
+    duckdb_create_list_value, duckdb_create_struct_value, duckdb_create_varchar,
+    duckdb_destroy_value, duckdb_free, duckdb_get_bool, duckdb_get_double, duckdb_get_int32,
+    duckdb_get_int64, duckdb_get_type_id, duckdb_get_value_type, duckdb_get_varchar,
+    duckdb_struct_type_child_count, duckdb_struct_type_child_name, duckdb_value,
+};
+use std::ffi::{CStr, CString};
+
+use crate::ffi::{
+    duckdb_ext_get_list_child, duckdb_ext_get_list_size, duckdb_ext_get_struct_child,
+    duckdb_ext_is_null_value,
+};
+use crate::{LogicalType, LogicalTypeId};
 
 /// The Value object holds a single arbitrary value of any type that can be
 /// stored in the database.
@@ -30,7 +42,7 @@ impl From<duckdb_value> for Value {
 
 impl Drop for Value {
     fn drop(&mut self) {
-        if self.ptr.is_null() {
+        if !self.ptr.is_null() {
             unsafe {
                 duckdb_destroy_value(&mut self.ptr);
             }
@@ -39,13 +51,159 @@ impl Drop for Value {
     }
 }
 
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::from(unsafe { duckdb_create_bool(value) })
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Self::from(unsafe { duckdb_create_int32(value) })
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::from(unsafe { duckdb_create_int64(value) })
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::from(unsafe { duckdb_create_double(value) })
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        let c_string = CString::new(value).unwrap();
+        Self::from(unsafe { duckdb_create_varchar(c_string.as_ptr()) })
+    }
+}
+
 impl Value {
-    pub fn to_string(&self) -> String {
-        let c_string = unsafe { CString::from_raw(duckdb_get_varchar(self.ptr)) };
-        c_string.into_string().unwrap()
+    /// Create a `LIST` value whose elements are all of `child_type`.
+    pub fn list(child_type: &LogicalType, values: &[Value]) -> Self {
+        let mut ptrs: Vec<duckdb_value> = values.iter().map(|v| v.ptr).collect();
+        Self::from(unsafe {
+            duckdb_create_list_value(child_type.ptr, ptrs.as_mut_ptr(), values.len() as u64)
+        })
     }
 
+    /// Create a `STRUCT` value of `struct_type`, with one value per field.
+    pub fn struct_value(struct_type: &LogicalType, values: &[Value]) -> Self {
+        assert_eq!(struct_type.num_children(), values.len());
+        let mut ptrs: Vec<duckdb_value> = values.iter().map(|v| v.ptr).collect();
+        Self::from(unsafe { duckdb_create_struct_value(struct_type.ptr, ptrs.as_mut_ptr()) })
+    }
+
+    /// The type of this value.
+    pub fn type_id(&self) -> LogicalTypeId {
+        // The type returned by duckdb_get_value_type is owned by the value.
+        unsafe { duckdb_get_type_id(duckdb_get_value_type(self.ptr)) }.into()
+    }
+
+    /// Whether this is a SQL `NULL`.
     pub fn is_null(&self) -> bool {
-        return self.ptr.is_null();
+        unsafe { duckdb_ext_is_null_value(self.ptr) }
+    }
+
+    pub fn to_bool(&self) -> bool {
+        unsafe { duckdb_get_bool(self.ptr) }
+    }
+
+    pub fn to_i32(&self) -> i32 {
+        unsafe { duckdb_get_int32(self.ptr) }
+    }
+
+    pub fn to_i64(&self) -> i64 {
+        unsafe { duckdb_get_int64(self.ptr) }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        unsafe { duckdb_get_double(self.ptr) }
+    }
+
+    /// The value as a `VARCHAR`, casting it if it is of another type.
+    pub fn to_varchar(&self) -> String {
+        unsafe { take_c_string(duckdb_get_varchar(self.ptr)) }
+    }
+
+    pub fn to_string(&self) -> String {
+        self.to_varchar()
+    }
+
+    /// The elements of a `LIST` value.
+    pub fn to_list(&self) -> Vec<Value> {
+        let len = unsafe { duckdb_ext_get_list_size(self.ptr) };
+        (0..len)
+            .map(|idx| Value::from(unsafe { duckdb_ext_get_list_child(self.ptr, idx) }))
+            .collect()
+    }
+
+    /// The fields of a `STRUCT` value, as `(name, value)` pairs.
+    pub fn to_struct(&self) -> Vec<(String, Value)> {
+        assert_eq!(self.type_id(), LogicalTypeId::Struct);
+        unsafe {
+            let struct_type = duckdb_get_value_type(self.ptr);
+            (0..duckdb_struct_type_child_count(struct_type))
+                .map(|idx| {
+                    let name = take_c_string(duckdb_struct_type_child_name(struct_type, idx));
+                    let value = Value::from(duckdb_ext_get_struct_child(self.ptr, idx));
+                    (name, value)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Copy a string allocated by DuckDB and free the original.
+pub(crate) unsafe fn take_c_string(ptr: *mut std::ffi::c_char) -> String {
+    let string = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+    duckdb_free(ptr.cast());
+    string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scalars() {
+        assert!(Value::from(true).to_bool());
+        assert_eq!(Value::from(42).to_i32(), 42);
+        assert_eq!(Value::from(-7_i64).to_i64(), -7);
+        assert_eq!(Value::from(1.5).to_f64(), 1.5);
+        assert_eq!(Value::from("athena").to_varchar(), "athena");
+        assert_eq!(Value::from(42).to_varchar(), "42");
+        assert_eq!(Value::from(42).type_id(), LogicalTypeId::Integer);
+        assert!(!Value::from(42).is_null());
+    }
+
+    #[test]
+    fn test_list() {
+        let child_type = LogicalType::new(LogicalTypeId::Bigint);
+        let list = Value::list(&child_type, &[Value::from(1_i64), Value::from(2_i64)]);
+        assert_eq!(list.type_id(), LogicalTypeId::List);
+
+        let items: Vec<i64> = list.to_list().iter().map(|v| v.to_i64()).collect();
+        assert_eq!(items, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_struct() {
+        let struct_type = LogicalType::struct_type(&[
+            ("name", LogicalType::new(LogicalTypeId::Varchar)),
+            ("count", LogicalType::new(LogicalTypeId::Integer)),
+        ]);
+        let value = Value::struct_value(&struct_type, &[Value::from("a"), Value::from(3)]);
+
+        let fields = value.to_struct();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].0, "name");
+        assert_eq!(fields[0].1.to_varchar(), "a");
+        assert_eq!(fields[1].0, "count");
+        assert_eq!(fields[1].1.to_i32(), 3);
     }
 }
@@ -13,17 +13,23 @@
 // limitations under the License.
 
 use std::any::Any;
-use std::ffi::CString;
+use std::ffi::{c_char, CString};
+use std::fmt;
 use std::slice;
+use std::str::FromStr;
 
 use libduckdb_sys::{
+    duckdb_decimal_internal_type, duckdb_decimal_scale, duckdb_enum_internal_type, duckdb_hugeint,
     duckdb_list_entry, duckdb_list_vector_get_child, duckdb_list_vector_get_size,
     duckdb_list_vector_reserve, duckdb_list_vector_set_size, duckdb_struct_type_child_count,
-    duckdb_struct_type_child_name, duckdb_struct_vector_get_child, duckdb_vector,
-    duckdb_vector_assign_string_element, duckdb_vector_get_column_type, duckdb_vector_get_data,
-    duckdb_vector_size,
+    duckdb_struct_type_child_name, duckdb_struct_vector_get_child, duckdb_validity_row_is_valid,
+    duckdb_validity_set_row_invalid, duckdb_vector, duckdb_vector_assign_string_element_len,
+    duckdb_vector_ensure_validity_writable, duckdb_vector_get_column_type, duckdb_vector_get_data,
+    duckdb_vector_get_validity, duckdb_vector_size, DUCKDB_TYPE_DUCKDB_TYPE_BIGINT,
+    DUCKDB_TYPE_DUCKDB_TYPE_INTEGER, DUCKDB_TYPE_DUCKDB_TYPE_SMALLINT,
+    DUCKDB_TYPE_DUCKDB_TYPE_USMALLINT, DUCKDB_TYPE_DUCKDB_TYPE_UTINYINT,
 };
-use crate::LogicalType;
+use crate::{Error, LogicalType, LogicalTypeId, Result};
 
 /// Vector trait.
 pub trait Vector {
@@ -86,6 +92,318 @@ impl FlatVector {
         assert!(data.len() <= self.capacity());
         self.as_mut_slice::<T>()[0..data.len()].copy_from_slice(data);
     }
+
+    /// Whether the value at `row` is NULL.
+    pub fn is_null(&self, row: usize) -> bool {
+        unsafe {
+            let validity = duckdb_vector_get_validity(self.ptr);
+            // No validity mask means every row is valid
+            !validity.is_null() && !duckdb_validity_row_is_valid(validity, row as u64)
+        }
+    }
+
+    /// Read the `VARCHAR` (or `BLOB`) value at `row`.
+    pub fn get_string(&self, row: usize) -> String {
+        // Layout of duckdb_string_t: a u32 length followed by either the
+        // string itself (up to 12 bytes) or a 4 byte prefix and a pointer.
+        unsafe {
+            let base = self.as_mut_ptr::<u8>().add(row * 16);
+            let len = base.cast::<u32>().read_unaligned() as usize;
+            let data = if len <= 12 {
+                base.add(4)
+            } else {
+                base.add(8).cast::<*const u8>().read_unaligned()
+            };
+            String::from_utf8_lossy(slice::from_raw_parts(data, len)).into_owned()
+        }
+    }
+
+    /// Mark the value at `row` as NULL.
+    pub fn set_null(&self, row: usize) {
+        unsafe {
+            duckdb_vector_ensure_validity_writable(self.ptr);
+            let validity = duckdb_vector_get_validity(self.ptr);
+            duckdb_validity_set_row_invalid(validity, row as u64);
+        }
+    }
+
+    /// Write `value` at `row`, failing if the vector's logical type can't hold it.
+    pub fn set<T: VectorValue>(&self, row: usize, value: T) -> Result<()> {
+        let logical_type = self.logical_type();
+        if !T::accepts(&logical_type) {
+            return Err(Error::DuckDB(format!(
+                "cannot write {} into a {:?} vector",
+                std::any::type_name::<T>(),
+                logical_type
+            )));
+        }
+        assert!(row < self.capacity());
+        value.write(self, &logical_type, row)
+    }
+
+    fn assign_bytes(&self, index: usize, bytes: &[u8]) {
+        unsafe {
+            duckdb_vector_assign_string_element_len(
+                self.ptr,
+                index as u64,
+                bytes.as_ptr().cast::<c_char>(),
+                bytes.len() as u64,
+            );
+        }
+    }
+}
+
+/// A `DECIMAL` value, `value` being the number scaled up by `10^scale`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    pub value: i128,
+    pub scale: u8,
+}
+
+impl Decimal {
+    /// The unscaled value at another scale, rounding digits that don't fit half
+    /// away from zero as DuckDB's own casts do, or `None` if it overflows.
+    pub fn rescale(&self, scale: u8) -> Option<i128> {
+        if scale >= self.scale {
+            return 10_i128
+                .checked_pow((scale - self.scale) as u32)
+                .and_then(|factor| self.value.checked_mul(factor));
+        }
+        // Past 10^38 every i128 rounds to 0
+        let Some(factor) = 10_i128.checked_pow((self.scale - scale) as u32) else {
+            return Some(0);
+        };
+        let (quotient, remainder) = (self.value / factor, self.value % factor);
+        if remainder.unsigned_abs() * 2 >= factor.unsigned_abs() {
+            Some(quotient + self.value.signum())
+        } else {
+            Some(quotient)
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = Error;
+
+    /// Parse a plain decimal number such as `-123.4500`.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::DuckDB(format!("invalid decimal: {s}"));
+        let s = s.trim();
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        if frac.len() > 38 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let value = format!("{int}{frac}").parse().map_err(|_| invalid())?;
+        Ok(Self {
+            value,
+            scale: frac.len() as u8,
+        })
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = self.scale as usize;
+        let digits = format!("{:0>1$}", self.value.unsigned_abs(), scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        let sign = if self.value < 0 { "-" } else { "" };
+        if frac.is_empty() {
+            write!(f, "{sign}{int}")
+        } else {
+            write!(f, "{sign}{int}.{frac}")
+        }
+    }
+}
+
+/// A value [FlatVector::set] can write into vectors of a matching logical type.
+pub trait VectorValue {
+    /// Whether vectors of `logical_type` can hold this value.
+    fn accepts(logical_type: &LogicalType) -> bool;
+
+    /// Write the value at `row` of `vector`, whose type is `logical_type`.
+    fn write(self, vector: &FlatVector, logical_type: &LogicalType, row: usize) -> Result<()>;
+}
+
+macro_rules! primitive_vector_value {
+    ($($ty:ty => $id:ident),* $(,)?) => {
+        $(
+            impl VectorValue for $ty {
+                fn accepts(logical_type: &LogicalType) -> bool {
+                    logical_type.id() == LogicalTypeId::$id
+                }
+
+                fn write(
+                    self,
+                    vector: &FlatVector,
+                    _logical_type: &LogicalType,
+                    row: usize,
+                ) -> Result<()> {
+                    unsafe { vector.as_mut_ptr::<$ty>().add(row).write(self) };
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+primitive_vector_value!(
+    bool => Boolean,
+    i8 => Tinyint,
+    i16 => Smallint,
+    i32 => Integer,
+    i64 => Bigint,
+    u8 => UTinyint,
+    u16 => USmallint,
+    u32 => UInteger,
+    u64 => UBigint,
+    f32 => Float,
+    f64 => Double,
+);
+
+impl VectorValue for i128 {
+    fn accepts(logical_type: &LogicalType) -> bool {
+        logical_type.id() == LogicalTypeId::Hugeint
+    }
+
+    fn write(self, vector: &FlatVector, _logical_type: &LogicalType, row: usize) -> Result<()> {
+        let hugeint = duckdb_hugeint {
+            lower: self as u64,
+            upper: (self >> 64) as i64,
+        };
+        unsafe {
+            vector
+                .as_mut_ptr::<duckdb_hugeint>()
+                .add(row)
+                .write(hugeint)
+        };
+        Ok(())
+    }
+}
+
+impl VectorValue for Decimal {
+    fn accepts(logical_type: &LogicalType) -> bool {
+        logical_type.id() == LogicalTypeId::Decimal
+    }
+
+    /// Rescales to the vector's scale, rounding extra fractional digits, and
+    /// stores it in the vector's physical width, which depends on the precision
+    /// of the type. Values with more digits than the precision allows, after
+    /// rounding, are refused.
+    fn write(self, vector: &FlatVector, logical_type: &LogicalType, row: usize) -> Result<()> {
+        let width = logical_type.decimal_width();
+        let scale = logical_type.decimal_scale();
+        let out_of_range = || {
+            Error::DuckDB(format!(
+                "decimal {} doesn't fit DECIMAL({},{})",
+                self, width, scale
+            ))
+        };
+        let value = self.rescale(scale).ok_or_else(out_of_range)?;
+        // Widths go up to 38, within what an i128 holds
+        if value.unsigned_abs() >= 10_u128.pow(width as u32) {
+            return Err(out_of_range());
+        }
+        unsafe {
+            match duckdb_decimal_internal_type(logical_type.ptr) {
+                DUCKDB_TYPE_DUCKDB_TYPE_SMALLINT => {
+                    let value = i16::try_from(value).map_err(|_| out_of_range())?;
+                    vector.as_mut_ptr::<i16>().add(row).write(value)
+                }
+                DUCKDB_TYPE_DUCKDB_TYPE_INTEGER => {
+                    let value = i32::try_from(value).map_err(|_| out_of_range())?;
+                    vector.as_mut_ptr::<i32>().add(row).write(value)
+                }
+                DUCKDB_TYPE_DUCKDB_TYPE_BIGINT => {
+                    let value = i64::try_from(value).map_err(|_| out_of_range())?;
+                    vector.as_mut_ptr::<i64>().add(row).write(value)
+                }
+                _ => return value.write(vector, logical_type, row),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An `ENUM` value, given by its index in the type's dictionary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnumIndex(pub u32);
+
+impl VectorValue for EnumIndex {
+    fn accepts(logical_type: &LogicalType) -> bool {
+        logical_type.id() == LogicalTypeId::Enum
+    }
+
+    /// Stores the index in the vector's physical width, which depends on the
+    /// size of the dictionary.
+    fn write(self, vector: &FlatVector, logical_type: &LogicalType, row: usize) -> Result<()> {
+        let EnumIndex(index) = self;
+        unsafe {
+            match duckdb_enum_internal_type(logical_type.ptr) {
+                DUCKDB_TYPE_DUCKDB_TYPE_UTINYINT => {
+                    vector.as_mut_ptr::<u8>().add(row).write(index as u8)
+                }
+                DUCKDB_TYPE_DUCKDB_TYPE_USMALLINT => {
+                    vector.as_mut_ptr::<u16>().add(row).write(index as u16)
+                }
+                _ => vector.as_mut_ptr::<u32>().add(row).write(index),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `DATE` value, in days since the Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date(pub i32);
+
+impl VectorValue for Date {
+    fn accepts(logical_type: &LogicalType) -> bool {
+        logical_type.id() == LogicalTypeId::Date
+    }
+
+    fn write(self, vector: &FlatVector, _logical_type: &LogicalType, row: usize) -> Result<()> {
+        let Date(days) = self;
+        unsafe { vector.as_mut_ptr::<i32>().add(row).write(days) };
+        Ok(())
+    }
+}
+
+/// A `TIMESTAMP` value, in microseconds since the Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp(pub i64);
+
+impl VectorValue for Timestamp {
+    fn accepts(logical_type: &LogicalType) -> bool {
+        logical_type.id() == LogicalTypeId::Timestamp
+    }
+
+    fn write(self, vector: &FlatVector, _logical_type: &LogicalType, row: usize) -> Result<()> {
+        let Timestamp(micros) = self;
+        unsafe { vector.as_mut_ptr::<i64>().add(row).write(micros) };
+        Ok(())
+    }
+}
+
+impl VectorValue for &str {
+    fn accepts(logical_type: &LogicalType) -> bool {
+        logical_type.id() == LogicalTypeId::Varchar
+    }
+
+    fn write(self, vector: &FlatVector, _logical_type: &LogicalType, row: usize) -> Result<()> {
+        vector.assign_bytes(row, self.as_bytes());
+        Ok(())
+    }
+}
+
+impl VectorValue for &[u8] {
+    fn accepts(logical_type: &LogicalType) -> bool {
+        logical_type.id() == LogicalTypeId::Blob
+    }
+
+    fn write(self, vector: &FlatVector, _logical_type: &LogicalType, row: usize) -> Result<()> {
+        vector.assign_bytes(row, self);
+        Ok(())
+    }
 }
 
 pub trait Inserter<T> {
@@ -94,10 +412,13 @@ pub trait Inserter<T> {
 
 impl Inserter<&str> for FlatVector {
     fn insert(&self, index: usize, value: &str) {
-        let cstr = CString::new(value.as_bytes()).unwrap();
-        unsafe {
-            duckdb_vector_assign_string_element(self.ptr, index as u64, cstr.as_ptr());
-        }
+        self.assign_bytes(index, value.as_bytes());
+    }
+}
+
+impl Inserter<&[u8]> for FlatVector {
+    fn insert(&self, index: usize, value: &[u8]) {
+        self.assign_bytes(index, value);
     }
 }
 
@@ -153,6 +474,66 @@ impl ListVector {
     }
 }
 
+/// A `MAP` vector, stored as a list of key/value structs per row.
+pub struct MapVector {
+    entries: ListVector,
+}
+
+impl From<duckdb_vector> for MapVector {
+    fn from(ptr: duckdb_vector) -> Self {
+        Self {
+            entries: ListVector::from(ptr),
+        }
+    }
+}
+
+/// The map held by a vector of `MAP` type, such as the values of a map of maps.
+impl From<FlatVector> for MapVector {
+    fn from(vector: FlatVector) -> Self {
+        Self::from(vector.ptr)
+    }
+}
+
+impl MapVector {
+    /// Total number of key/value entries across all rows.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The keys of all entries, with room for `capacity` of them.
+    pub fn keys(&self, capacity: usize) -> FlatVector {
+        self.entry_field(0, capacity)
+    }
+
+    /// The values of all entries, with room for `capacity` of them.
+    pub fn values(&self, capacity: usize) -> FlatVector {
+        self.entry_field(1, capacity)
+    }
+
+    fn entry_field(&self, idx: usize, capacity: usize) -> FlatVector {
+        self.entries.reserve(capacity);
+        unsafe {
+            let entries = duckdb_list_vector_get_child(self.entries.entries.ptr);
+            let field = duckdb_struct_vector_get_child(entries, idx as u64);
+            FlatVector::with_capacity(field, capacity)
+        }
+    }
+
+    /// Make the map at row `idx` hold the `length` entries starting at `offset`.
+    pub fn set_entry(&mut self, idx: usize, offset: usize, length: usize) {
+        self.entries.set_entry(idx, offset, length);
+    }
+
+    /// Set the total number of entries.
+    pub fn set_len(&self, new_len: usize) {
+        self.entries.set_len(new_len);
+    }
+}
+
 pub struct StructVector {
     /// ListVector does not own the vector pointer.
     ptr: duckdb_vector,
@@ -199,3 +580,128 @@ impl StructVector {
         unsafe { duckdb_struct_type_child_count(logical_type.ptr) as usize }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataChunk;
+
+    #[test]
+    fn test_map() {
+        let map_type = LogicalType::map_type(
+            &LogicalType::new(LogicalTypeId::Varchar),
+            &LogicalType::new(LogicalTypeId::Integer),
+        );
+        let chunk = DataChunk::new(&[map_type]);
+
+        let mut map = chunk.map_vector(0);
+        let keys = map.keys(2);
+        let values = map.values(2);
+        keys.insert(0, "a");
+        keys.insert(1, "b");
+        values.set(0, 1).unwrap_or_else(|e| panic!("{}", e));
+        values.set(1, 2).unwrap_or_else(|e| panic!("{}", e));
+        map.set_entry(0, 0, 2);
+        map.set_len(2);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.keys(2).get_string(1), "b");
+        assert_eq!(map.values(2).as_slice::<i32>()[..2], [1, 2]);
+    }
+
+    #[test]
+    fn test_enum() {
+        let enum_type = LogicalType::enum_type(&["active", "closed"]);
+        assert_eq!(enum_type.enum_values(), vec!["active", "closed"]);
+
+        let chunk = DataChunk::new(&[enum_type]);
+        let vector = chunk.flat_vector(0);
+        vector
+            .set(0, EnumIndex(1))
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(vector.as_slice::<u8>()[0], 1);
+    }
+
+    #[test]
+    fn test_set() {
+        let chunk = DataChunk::new(&[
+            LogicalType::new(LogicalTypeId::Blob),
+            LogicalType::new(LogicalTypeId::Hugeint),
+            LogicalType::decimal(38, 2),
+            LogicalType::decimal(9, 2),
+        ]);
+
+        let blob = chunk.flat_vector(0);
+        blob.set(0, &b"a\0b"[..])
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(blob.get_string(0), "a\0b");
+        assert!(blob.set(1, 42_i64).is_err());
+
+        chunk
+            .flat_vector(1)
+            .set(0, -2_i128)
+            .unwrap_or_else(|e| panic!("{}", e));
+        let hugeint = chunk.flat_vector(1).as_slice::<duckdb_hugeint>()[0];
+        assert_eq!((hugeint.upper, hugeint.lower), (-1, u64::MAX - 1));
+
+        let wide = chunk.flat_vector(2);
+        let value: Decimal = "123.125".parse().unwrap_or_else(|e| panic!("{}", e));
+        wide.set(0, value).unwrap_or_else(|e| panic!("{}", e));
+        let hugeint = wide.as_slice::<duckdb_hugeint>()[0];
+        assert_eq!((hugeint.upper, hugeint.lower), (0, 12312));
+
+        let narrow = chunk.flat_vector(3);
+        let value: Decimal = "-1.5".parse().unwrap_or_else(|e| panic!("{}", e));
+        narrow.set(0, value).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(narrow.as_slice::<i32>()[0], -150);
+    }
+
+    #[test]
+    fn test_decimal_out_of_range() {
+        let chunk = DataChunk::new(&[
+            LogicalType::decimal(4, 2),
+            LogicalType::decimal(9, 0),
+            LogicalType::decimal(38, 38),
+        ]);
+        let decimal = |s: &str| -> Decimal { s.parse().unwrap_or_else(|e| panic!("{}", e)) };
+
+        let small = chunk.flat_vector(0);
+        small
+            .set(0, decimal("99.994"))
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(small.as_slice::<i16>()[0], 9999);
+        // Rounds up to 100.00, past the precision
+        assert!(small.set(0, decimal("99.995")).is_err());
+        assert!(small.set(0, decimal("99.999")).is_err());
+        assert!(small.set(0, decimal("100")).is_err());
+        assert!(small.set(0, decimal("-327.68")).is_err());
+
+        let integer = chunk.flat_vector(1);
+        assert!(integer.set(0, decimal("1000000000")).is_err());
+        assert!(integer.set(0, decimal("4294967296")).is_err());
+
+        // The scale multiply itself overflows an i128
+        let fraction = chunk.flat_vector(2);
+        assert!(fraction.set(0, decimal("12345678901234567890")).is_err());
+    }
+
+    #[test]
+    fn test_decimal() {
+        let decimal = |s: &str| -> Decimal { s.parse().unwrap_or_else(|e| panic!("{}", e)) };
+        assert_eq!(decimal("-1.50").to_string(), "-1.50");
+        assert_eq!(decimal("0.05").to_string(), "0.05");
+        assert_eq!(decimal("-0.05").to_string(), "-0.05");
+        assert_eq!(decimal("42").to_string(), "42");
+
+        assert_eq!(decimal("1.25").rescale(4), Some(12500));
+        assert_eq!(decimal("1.25").rescale(1), Some(13));
+        assert_eq!(decimal("-1.25").rescale(1), Some(-13));
+        assert_eq!(decimal("-1.24").rescale(1), Some(-12));
+        assert_eq!(decimal("1.25").rescale(0), Some(1));
+        assert_eq!(decimal("1.5").rescale(0), Some(2));
+        assert_eq!(decimal("-1.5").rescale(0), Some(-2));
+        assert_eq!(decimal("0.5").rescale(0), Some(1));
+        assert_eq!(decimal("1").rescale(38), Some(10_i128.pow(38)));
+        assert_eq!(decimal("2").rescale(38), None);
+    }
+}
@@ -0,0 +1,263 @@
+// Copyright 2023 Lance Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A safe, trait-based way of writing table functions.
+//!
+//! Implement [VTab] and register it with [crate::Connection::register_vtab];
+//! the `extern "C"` callbacks DuckDB calls into are generated for it.
+
+use std::ffi::c_void;
+
+use libduckdb_sys::{duckdb_bind_info, duckdb_data_chunk, duckdb_function_info, duckdb_init_info};
+
+use crate::table_function::{BindInfo, InitInfo, TableFunction};
+use crate::{DataChunk, FunctionInfo, LogicalType, Result};
+
+/// The state a [VTab::func] call scans from.
+pub struct ScanState<'a, T: VTab> {
+    /// What [VTab::bind] returned.
+    pub bind_data: &'a T::BindData,
+    /// What [VTab::init] returned, shared by all threads scanning in parallel.
+    pub init_data: &'a T::InitData,
+    /// What [VTab::local_init] returned for the calling thread, if anything.
+    pub local_init_data: Option<&'a mut T::LocalInitData>,
+}
+
+/// A table function.
+///
+/// DuckDB may scan from several threads at once, see
+/// [InitInfo::set_max_threads], so the bind and init data are shared between
+/// threads and the local init data may be dropped on another thread than the
+/// one that created it.
+pub trait VTab: Sized {
+    /// State built when the function call is bound, e.g. its parameters.
+    type BindData: Send + Sync + 'static;
+    /// State shared by all threads running a scan.
+    type InitData: Send + Sync + 'static;
+    /// State private to each thread running a scan, `()` if there is none.
+    type LocalInitData: Send + 'static;
+
+    /// Declare the result columns and turn the parameters into [Self::BindData].
+    fn bind(bind: &BindInfo) -> Result<Self::BindData>;
+
+    /// Set up a scan. Runs once per scan, before any call to [Self::func].
+    fn init(init: &InitInfo, bind_data: &Self::BindData) -> Result<Self::InitData>;
+
+    /// Set up the calling thread for a scan. Runs once per scanning thread.
+    fn local_init(
+        _init: &InitInfo,
+        _bind_data: &Self::BindData,
+    ) -> Result<Option<Self::LocalInitData>> {
+        Ok(None)
+    }
+
+    /// Fill `output` with the next chunk of rows, leaving it empty once the scan is done.
+    fn func(state: &mut ScanState<Self>, output: &mut DataChunk) -> Result<()>;
+
+    /// Types of the positional parameters.
+    fn parameters() -> Vec<LogicalType> {
+        Vec::new()
+    }
+
+    /// Names and types of the named parameters.
+    fn named_parameters() -> Vec<(&'static str, LogicalType)> {
+        Vec::new()
+    }
+
+    /// Percentage of the scan done, between 0 and 100, for DuckDB's progress bar.
+    fn progress(_bind_data: &Self::BindData, _init_data: &Self::InitData) -> Option<f64> {
+        None
+    }
+}
+
+unsafe extern "C" fn bind<T: VTab>(info: duckdb_bind_info) {
+    let info = BindInfo::from(info);
+    match T::bind(&info) {
+        Ok(data) => info.set_bind_data(data),
+        Err(e) => info.set_error(e),
+    }
+}
+
+unsafe extern "C" fn init<T: VTab>(info: duckdb_init_info) {
+    let info = InitInfo::from(info);
+    match T::init(&info, info.bind_data::<T::BindData>()) {
+        Ok(data) => info.set_init_data(data),
+        Err(e) => info.set_error(e),
+    }
+}
+
+unsafe extern "C" fn local_init<T: VTab>(info: duckdb_init_info) {
+    let info = InitInfo::from(info);
+    match T::local_init(&info, info.bind_data::<T::BindData>()) {
+        Ok(Some(data)) => info.set_init_data(data),
+        Ok(None) => {}
+        Err(e) => info.set_error(e),
+    }
+}
+
+unsafe extern "C" fn func<T: VTab>(info: duckdb_function_info, output: duckdb_data_chunk) {
+    let info = FunctionInfo::from(info);
+    let mut output = DataChunk::from(output);
+    let mut state = ScanState::<T> {
+        bind_data: info.bind_data(),
+        init_data: info.init_data(),
+        local_init_data: info.local_init_data(),
+    };
+    if let Err(e) = T::func(&mut state, &mut output) {
+        info.set_error(e);
+    }
+}
+
+unsafe extern "C" fn progress<T: VTab>(bind_data: *mut c_void, init_data: *mut c_void) -> f64 {
+    if bind_data.is_null() || init_data.is_null() {
+        return -1.0;
+    }
+    let bind_data = &*bind_data.cast::<T::BindData>();
+    let init_data = &*init_data.cast::<T::InitData>();
+    T::progress(bind_data, init_data).unwrap_or(-1.0)
+}
+
+/// The [TableFunction] that runs `T` under `name`.
+pub(crate) fn table_function<T: VTab>(name: &str) -> TableFunction {
+    let table_function = TableFunction::new(name);
+    for logical_type in T::parameters() {
+        table_function.add_parameter(&logical_type);
+    }
+    for (name, logical_type) in T::named_parameters() {
+        table_function.add_named_parameter(name, &logical_type);
+    }
+    table_function
+        .set_bind(Some(bind::<T>))
+        .set_init(Some(init::<T>))
+        .set_local_init(Some(local_init::<T>))
+        .set_function(Some(func::<T>))
+        .set_progress(Some(progress::<T>));
+    table_function
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, Error, LogicalTypeId, SharedDatabase};
+
+    /// Counts from 0 to the bound limit, one vector at a time.
+    struct Range;
+
+    impl VTab for Range {
+        type BindData = i64;
+        type InitData = std::sync::atomic::AtomicI64;
+        type LocalInitData = ();
+
+        fn bind(bind: &BindInfo) -> Result<i64> {
+            bind.add_result_column("i", LogicalType::new(LogicalTypeId::Bigint));
+            Ok(bind.parameter(0).to_i64())
+        }
+
+        fn init(_init: &InitInfo, _bind_data: &i64) -> Result<Self::InitData> {
+            Ok(Default::default())
+        }
+
+        fn func(state: &mut ScanState<Self>, output: &mut DataChunk) -> Result<()> {
+            use std::sync::atomic::Ordering;
+
+            let start = state.init_data.fetch_add(2, Ordering::SeqCst);
+            let end = (start + 2).min(*state.bind_data);
+            let values: Vec<i64> = (start..end).collect();
+            output.flat_vector(0).copy(&values);
+            output.set_len(values.len());
+            Ok(())
+        }
+
+        fn parameters() -> Vec<LogicalType> {
+            vec![LogicalType::new(LogicalTypeId::Bigint)]
+        }
+    }
+
+    /// Counts the rows of table `t` in the database it is bound in.
+    struct CountRows;
+
+    impl VTab for CountRows {
+        type BindData = SharedDatabase;
+        type InitData = std::sync::atomic::AtomicBool;
+        type LocalInitData = ();
+
+        fn bind(bind: &BindInfo) -> Result<SharedDatabase> {
+            bind.add_result_column("n", LogicalType::new(LogicalTypeId::Bigint));
+            bind.database()
+                .ok_or_else(|| Error::DuckDB("no database".to_string()))
+        }
+
+        fn init(_init: &InitInfo, _bind_data: &SharedDatabase) -> Result<Self::InitData> {
+            Ok(Default::default())
+        }
+
+        fn func(state: &mut ScanState<Self>, output: &mut DataChunk) -> Result<()> {
+            if state
+                .init_data
+                .swap(true, std::sync::atomic::Ordering::SeqCst)
+            {
+                output.set_len(0);
+                return Ok(());
+            }
+            let mut result = state.bind_data.connect()?.query("SELECT count(*) FROM t")?;
+            output.flat_vector(0).set(0, result.rows()[0][0].to_i64())?;
+            output.set_len(1);
+            Ok(())
+        }
+    }
+
+    fn ok<T>(result: Result<T>) -> T {
+        result.unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[test]
+    fn test_bound_database() {
+        // Each database's scans query that database, whichever was opened last
+        let connections: Vec<_> = [1, 3]
+            .into_iter()
+            .map(|rows| {
+                let connection = ok(ok(Database::open_in_memory()).connect());
+                ok(connection.query(&format!("CREATE TABLE t AS SELECT * FROM range({rows})")));
+                connection.register_vtab::<CountRows>("count_rows").unwrap();
+                connection
+            })
+            .collect();
+        for (connection, rows) in connections.iter().zip([1, 3]) {
+            let mut result = ok(connection.query("SELECT n FROM count_rows()"));
+            assert_eq!(result.rows()[0][0].to_i64(), rows);
+        }
+    }
+
+    #[test]
+    fn test_func() {
+        let bind_data = 3;
+        let init_data = Default::default();
+        let mut state = ScanState::<Range> {
+            bind_data: &bind_data,
+            init_data: &init_data,
+            local_init_data: None,
+        };
+        let mut output = DataChunk::new(&[LogicalType::new(LogicalTypeId::Bigint)]);
+
+        let mut lens = Vec::new();
+        loop {
+            Range::func(&mut state, &mut output).unwrap_or_else(|e| panic!("{}", e));
+            if output.is_empty() {
+                break;
+            }
+            lens.push(output.len());
+        }
+        assert_eq!(lens, vec![2, 1]);
+    }
+}
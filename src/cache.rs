@@ -0,0 +1,532 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Result};
+use duckdb_athena_rust::table_function::{BindInfo, InitInfo};
+use duckdb_athena_rust::vtab::{ScanState, VTab};
+use duckdb_athena_rust::{
+    Appender, Connection, DataChunk, Database, LogicalType, LogicalTypeId, QueryResult,
+};
+
+use crate::config::AthenaConfig;
+
+/// Cached results are stored as Parquet files named after their key.
+const EXTENSION: &str = "parquet";
+
+/// Temporary table rows are collected in before being written to the cache.
+const STAGING_TABLE: &str = "athena_cache_staging";
+
+/// Files entries are written under before being renamed into place.
+const STAGING_EXTENSION: &str = "tmp";
+
+/// Age past which a staged file no writer is still working on is removed, left
+/// behind by a process that exited while writing it.
+const STAGING_TTL: Duration = Duration::from_secs(60 * 60);
+
+static STAGING_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Where converted results are kept, and for how long.
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Whether scans read from and write to the cache at all
+    pub enabled: bool,
+    pub directory: PathBuf,
+    pub ttl: Duration,
+    /// Total size the cache is trimmed to after each write, in bytes
+    pub max_size: u64,
+}
+
+impl CacheConfig {
+    fn path(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{key}.{EXTENSION}"))
+    }
+
+    /// The cached results for `key`, if there are any younger than the TTL.
+    pub fn lookup(&self, key: &str) -> Option<PathBuf> {
+        let path = self.path(key);
+        let age = fs::metadata(&path).ok()?.modified().ok()?.elapsed().ok()?;
+        (age <= self.ttl).then_some(path)
+    }
+
+    /// The distinct values of a column cached under `key`, if they are younger
+    /// than the TTL, read through `database`.
+    pub fn lookup_values(&self, database: &Database, key: &str) -> Option<Vec<String>> {
+        let path = self.lookup(key)?;
+        let connection = database.connect().ok()?;
+        let mut result = connection
+            .query(&format!(
+                "SELECT value FROM read_parquet({}) ORDER BY idx",
+                quote_path(&path)
+            ))
+            .ok()?;
+        Some(
+            result
+                .rows()
+                .iter()
+                .map(|row| row[0].to_varchar())
+                .collect(),
+        )
+    }
+
+    /// Cache the distinct values of a column under `key`, in order, writing
+    /// them through `database`.
+    pub fn store_values(&self, database: &Database, key: &str, values: &[String]) -> Result<()> {
+        let rows: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| format!("({}, '{}')", idx, value.replace('\'', "''")))
+            .collect();
+        let select = if rows.is_empty() {
+            "SELECT 0 AS idx, '' AS value WHERE false".to_string()
+        } else {
+            format!("SELECT * FROM (VALUES {}) t(idx, value)", rows.join(", "))
+        };
+        fs::create_dir_all(&self.directory)?;
+
+        let staged = StagedFile::new(&self.path(key));
+        database
+            .connect()
+            .map_err(|e| anyhow!(e.to_string()))?
+            .query(&format!(
+                "COPY ({}) TO {} (FORMAT PARQUET)",
+                select,
+                quote_path(&staged.path)
+            ))
+            .map_err(|e| anyhow!(e.to_string()))?;
+        staged.commit()?;
+        self.evict()
+    }
+
+    /// Remove expired entries, then the oldest ones until the cache fits in
+    /// `max_size`, and staged files left behind by writers that didn't finish.
+    fn evict(&self) -> Result<()> {
+        for path in stale_staged_files(&self.directory)? {
+            fs::remove_file(path)?;
+        }
+
+        let mut entries = cache_entries(&self.directory)?;
+        entries.sort_by_key(|entry| entry.modified);
+
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        for entry in entries {
+            let expired = entry.modified.elapsed().map_or(false, |age| age > self.ttl);
+            if expired || total > self.max_size {
+                fs::remove_file(&entry.path)?;
+                total -= entry.size;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Key of the results of `query`, from everything they depend on. The SQL is
+/// compared with its whitespace normalized.
+pub fn cache_key(query: &str, parts: &[&str]) -> String {
+    let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    // 64-bit FNV-1a, which unlike std's hasher is stable across builds
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in std::iter::once(query.as_str()).chain(parts.iter().copied()) {
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+struct CacheEntry {
+    path: PathBuf,
+    modified: SystemTime,
+    size: u64,
+}
+
+fn cache_entries(directory: &Path) -> Result<Vec<CacheEntry>> {
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().map_or(true, |ext| ext != EXTENSION) {
+            continue;
+        }
+        let metadata = fs::metadata(&path)?;
+        entries.push(CacheEntry {
+            path,
+            modified: metadata.modified()?,
+            size: metadata.len(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Staged files in `directory` older than [STAGING_TTL].
+fn stale_staged_files(directory: &Path) -> Result<Vec<PathBuf>> {
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+    let mut stale = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .map_or(true, |ext| ext != STAGING_EXTENSION)
+        {
+            continue;
+        }
+        let age = fs::metadata(&path)?.modified()?.elapsed();
+        if age.map_or(false, |age| age > STAGING_TTL) {
+            stale.push(path);
+        }
+    }
+    Ok(stale)
+}
+
+/// Remove all cached results in `directory`, returning how many there were and
+/// their total size.
+fn clear(directory: &Path) -> Result<(usize, u64)> {
+    let entries = cache_entries(directory)?;
+    let size = entries.iter().map(|entry| entry.size).sum();
+    for entry in &entries {
+        fs::remove_file(&entry.path)?;
+    }
+    Ok((entries.len(), size))
+}
+
+/// A name to write the cache entry at `path` under before renaming it, unique
+/// to the writer, as scans of the same query may finish at the same time.
+fn staging_path(path: &Path) -> PathBuf {
+    path.with_extension(format!(
+        "{}-{}.{STAGING_EXTENSION}",
+        std::process::id(),
+        STAGING_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// A cache entry being written, removed unless it is committed.
+struct StagedFile {
+    path: PathBuf,
+    target: PathBuf,
+}
+
+impl StagedFile {
+    fn new(target: &Path) -> Self {
+        Self {
+            path: staging_path(target),
+            target: target.to_path_buf(),
+        }
+    }
+
+    /// Move the written file into place, so that readers never see a partial file.
+    fn commit(self) -> Result<()> {
+        fs::rename(&self.path, &self.target)?;
+        Ok(())
+    }
+}
+
+impl Drop for StagedFile {
+    fn drop(&mut self) {
+        // Gone once committed, and possibly never created if the write failed early
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn quote_path(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "''"))
+}
+
+/// Collects the chunks of a scan, and writes them to the cache once the scan
+/// has read all of the results.
+pub struct CacheWriter {
+    // Declared first to be dropped before the connection it appends through
+    appender: Appender,
+    connection: Connection,
+    config: CacheConfig,
+    path: PathBuf,
+}
+
+impl CacheWriter {
+    /// Start collecting results for `key`, whose columns are declared as
+    /// `columns`, in a temporary table of `database`.
+    pub fn new(
+        database: &Database,
+        config: &CacheConfig,
+        key: &str,
+        columns: &[String],
+    ) -> Result<Self> {
+        let connection = database.connect().map_err(|e| anyhow!(e.to_string()))?;
+        connection
+            .query(&format!(
+                "CREATE TEMP TABLE {STAGING_TABLE} ({})",
+                columns.join(", ")
+            ))
+            .map_err(|e| anyhow!(e.to_string()))?;
+        let appender = connection
+            .appender(None, STAGING_TABLE)
+            .map_err(|e| anyhow!(e.to_string()))?;
+
+        Ok(Self {
+            appender,
+            connection,
+            config: config.clone(),
+            path: config.path(key),
+        })
+    }
+
+    pub fn append(&mut self, chunk: &DataChunk) -> Result<()> {
+        self.appender
+            .append_chunk(chunk)
+            .map_err(|e| anyhow!(e.to_string()))
+    }
+
+    /// Write the collected rows to the cache and trim it to its maximum size.
+    pub fn finish(mut self) -> Result<()> {
+        self.appender.flush().map_err(|e| anyhow!(e.to_string()))?;
+        fs::create_dir_all(&self.config.directory)?;
+
+        let staged = StagedFile::new(&self.path);
+        self.connection
+            .query(&format!(
+                "COPY {STAGING_TABLE} TO {} (FORMAT PARQUET)",
+                quote_path(&staged.path)
+            ))
+            .map_err(|e| anyhow!(e.to_string()))?;
+        staged.commit()?;
+
+        self.config.evict()
+    }
+}
+
+/// Results read back from the cache.
+pub struct CachedResults {
+    result: QueryResult,
+    _connection: Connection,
+}
+
+impl CachedResults {
+    pub fn open(database: &Database, path: &Path) -> Result<Self> {
+        let connection = database.connect().map_err(|e| anyhow!(e.to_string()))?;
+        let result = connection
+            .query(&format!("SELECT * FROM read_parquet({})", quote_path(path)))
+            .map_err(|e| anyhow!("could not read cached results {}: {}", path.display(), e))?;
+        Ok(Self {
+            result,
+            _connection: connection,
+        })
+    }
+
+    pub fn next_chunk(&mut self) -> Option<DataChunk> {
+        self.result.fetch_chunk()
+    }
+}
+
+/// `athena_cache_clear()`: delete all cached results.
+struct CacheClear;
+
+impl VTab for CacheClear {
+    type BindData = PathBuf;
+    /// Number and total size of the removed entries, until they were returned
+    type InitData = Mutex<Option<(usize, u64)>>;
+    type LocalInitData = ();
+
+    fn bind(bind: &BindInfo) -> duckdb_athena_rust::Result<PathBuf> {
+        bind.add_result_column("entries", LogicalType::new(LogicalTypeId::Bigint));
+        bind.add_result_column("bytes", LogicalType::new(LogicalTypeId::Bigint));
+        Ok(AthenaConfig::from_bind(bind).cache.directory)
+    }
+
+    fn init(_init: &InitInfo, directory: &PathBuf) -> duckdb_athena_rust::Result<Self::InitData> {
+        let removed =
+            clear(directory).map_err(|e| duckdb_athena_rust::Error::DuckDB(e.to_string()))?;
+        Ok(Mutex::new(Some(removed)))
+    }
+
+    fn func(state: &mut ScanState<Self>, output: &mut DataChunk) -> duckdb_athena_rust::Result<()> {
+        match state.init_data.lock().expect("cache clear lock").take() {
+            Some((entries, bytes)) => {
+                output.flat_vector(0).set(0, entries as i64)?;
+                output.flat_vector(1).set(0, bytes as i64)?;
+                output.set_len(1);
+            }
+            None => output.set_len(0),
+        }
+        Ok(())
+    }
+}
+
+pub fn register_cache_clear(connection: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+    connection.register_vtab::<CacheClear>("athena_cache_clear")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory for a test to keep its cache in.
+    fn test_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "duckdb-athena-cache-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn config(directory: &Path, ttl: Duration, max_size: u64) -> CacheConfig {
+        CacheConfig {
+            enabled: true,
+            directory: directory.to_path_buf(),
+            ttl,
+            max_size,
+        }
+    }
+
+    /// Write a cache entry of `size` bytes, last modified `age` ago.
+    fn write_entry(config: &CacheConfig, key: &str, size: usize, age: Duration) -> PathBuf {
+        let path = config.path(key);
+        fs::write(&path, vec![0; size]).unwrap();
+        set_age(&path, age);
+        path
+    }
+
+    fn set_age(path: &Path, age: Duration) {
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_cache_key() {
+        // Keys name files that outlive the process, so they must never change
+        assert_eq!(cache_key("SELECT 1", &["us-east-1"]), "d125fa4613a2ba66");
+        assert_eq!(
+            cache_key("SELECT  1\n", &["us-east-1"]),
+            cache_key("SELECT 1", &["us-east-1"])
+        );
+        assert_ne!(
+            cache_key("SELECT 1", &["ab", "c"]),
+            cache_key("SELECT 1", &["a", "bc"])
+        );
+        assert_ne!(cache_key("SELECT 1", &[]), cache_key("SELECT 2", &[]));
+    }
+
+    #[test]
+    fn test_lookup() {
+        let directory = test_directory("lookup");
+        let config = config(&directory, Duration::from_secs(60), u64::MAX);
+        assert_eq!(config.lookup("missing"), None);
+
+        let fresh = write_entry(&config, "fresh", 10, Duration::ZERO);
+        write_entry(&config, "expired", 10, Duration::from_secs(120));
+        assert_eq!(config.lookup("fresh"), Some(fresh));
+        assert_eq!(config.lookup("expired"), None);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_evict_expired() {
+        let directory = test_directory("evict-expired");
+        let config = config(&directory, Duration::from_secs(60), u64::MAX);
+        let fresh = write_entry(&config, "fresh", 10, Duration::ZERO);
+        let expired = write_entry(&config, "expired", 10, Duration::from_secs(120));
+
+        config.evict().unwrap();
+        assert!(fresh.exists());
+        assert!(!expired.exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_evict_to_max_size() {
+        let directory = test_directory("evict-size");
+        let config = config(&directory, Duration::from_secs(3600), 250);
+        let oldest = write_entry(&config, "oldest", 100, Duration::from_secs(30));
+        let older = write_entry(&config, "older", 100, Duration::from_secs(20));
+        let newest = write_entry(&config, "newest", 100, Duration::from_secs(10));
+        // Files other than cache entries, such as staged ones, are left alone
+        let staged = directory.join("newest.1-0.tmp");
+        fs::write(&staged, vec![0; 1000]).unwrap();
+
+        config.evict().unwrap();
+        assert!(!oldest.exists());
+        assert!(older.exists());
+        assert!(newest.exists());
+        assert!(staged.exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_evict_stale_staged_files() {
+        let directory = test_directory("evict-staged");
+        let config = config(&directory, Duration::from_secs(3600), u64::MAX);
+        let writing = directory.join("a.1-0.tmp");
+        fs::write(&writing, vec![0; 10]).unwrap();
+        let abandoned = directory.join("b.1-1.tmp");
+        fs::write(&abandoned, vec![0; 10]).unwrap();
+        set_age(&abandoned, STAGING_TTL * 2);
+
+        config.evict().unwrap();
+        assert!(writing.exists());
+        assert!(!abandoned.exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_staged_file() {
+        let directory = test_directory("staged-file");
+        let target = directory.join("entry.parquet");
+
+        let failed = StagedFile::new(&target);
+        fs::write(&failed.path, b"partial").unwrap();
+        let failed_path = failed.path.clone();
+        drop(failed);
+        assert!(!failed_path.exists());
+        assert!(!target.exists());
+
+        let written = StagedFile::new(&target);
+        fs::write(&written.path, b"complete").unwrap();
+        let written_path = written.path.clone();
+        written.commit().unwrap();
+        assert!(!written_path.exists());
+        assert_eq!(fs::read(&target).unwrap(), b"complete");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_clear() {
+        let directory = test_directory("clear");
+        let config = config(&directory, Duration::from_secs(60), u64::MAX);
+        write_entry(&config, "a", 10, Duration::ZERO);
+        write_entry(&config, "b", 20, Duration::from_secs(120));
+
+        assert_eq!(clear(&directory).unwrap(), (2, 30));
+        assert_eq!(clear(&directory).unwrap(), (0, 0));
+        assert_eq!(clear(&directory.join("missing")).unwrap(), (0, 0));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_staging_path() {
+        let path = Path::new("/cache/0123456789abcdef.parquet");
+        let first = staging_path(path);
+        let second = staging_path(path);
+        assert_ne!(first, second);
+        assert_eq!(first.extension().unwrap(), "tmp");
+        assert_eq!(first.parent(), path.parent());
+    }
+}
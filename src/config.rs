@@ -0,0 +1,307 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use aws_config::SdkConfig;
+use aws_sdk_athena::Region;
+use aws_smithy_types::retry::RetryConfig;
+use duckdb_athena_rust::table_function::BindInfo;
+use duckdb_athena_rust::{Database, LogicalType, LogicalTypeId, SharedDatabase, Value};
+use tokio::time::Duration;
+
+use crate::cache::CacheConfig;
+use crate::cost::CostConfig;
+use crate::error::{Error, Result};
+use crate::logging::{self, warn, LogConfig, LogLevel, DEFAULT_LOG_LEVEL};
+use crate::retry::RetryPolicy;
+
+const MAXROWS: &str = "athena_maxrows";
+const POLL_INTERVAL: &str = "athena_poll_interval";
+const QUERY_TIMEOUT: &str = "athena_query_timeout";
+const WORKGROUP: &str = "athena_workgroup";
+const REGION: &str = "athena_region";
+const OUTPUT_LOCATION: &str = "athena_output_location";
+const REUSE_RESULTS_MAX_AGE: &str = "athena_reuse_results_max_age";
+const LOG_LEVEL: &str = "athena_log_level";
+const LOG_FILE: &str = "athena_log_file";
+const RETRY_MAX_ATTEMPTS: &str = "athena_retry_max_attempts";
+const RETRY_BASE_DELAY: &str = "athena_retry_base_delay_ms";
+const RETRY_MAX_DELAY: &str = "athena_retry_max_delay_ms";
+const CACHE_ENABLED: &str = "athena_cache_enabled";
+const CACHE_DIRECTORY: &str = "athena_cache_directory";
+const CACHE_TTL: &str = "athena_cache_ttl";
+const CACHE_MAX_SIZE: &str = "athena_cache_max_size_mb";
+const MAX_BYTES_SCANNED: &str = "athena_max_bytes_scanned";
+const SESSION_MAX_BYTES_SCANNED: &str = "athena_session_max_bytes_scanned";
+const PRICE_PER_TB: &str = "athena_price_per_tb";
+
+const DEFAULT_MAXROWS: i32 = 10000;
+const DEFAULT_POLL_INTERVAL_SECS: i32 = 5;
+const DEFAULT_WORKGROUP: &str = "primary";
+/// Longest Athena reuses query results for, in minutes.
+pub const MAX_REUSE_RESULTS_MAX_AGE: i32 = 7 * 24 * 60;
+const DEFAULT_RETRY_MAX_ATTEMPTS: i32 = 5;
+const DEFAULT_RETRY_BASE_DELAY_MS: i32 = 100;
+const DEFAULT_RETRY_MAX_DELAY_MS: i32 = 20_000;
+const DEFAULT_CACHE_TTL_SECS: i32 = 24 * 60 * 60;
+const DEFAULT_CACHE_MAX_SIZE_MB: i32 = 1024;
+/// Athena's price in most regions, in US dollars.
+const DEFAULT_PRICE_PER_TB: f64 = 5.0;
+
+/// Register the extension's `SET` options.
+pub fn register_options(db: &Database) -> Result<()> {
+    db.add_config_option(
+        MAXROWS,
+        "Rows athena_scan returns when maxrows is not given, -1 for all of them",
+        &LogicalType::new(LogicalTypeId::Integer),
+        Value::from(DEFAULT_MAXROWS),
+    )?;
+    db.add_config_option(
+        POLL_INTERVAL,
+        "Most seconds between checks of whether an Athena query has finished",
+        &LogicalType::new(LogicalTypeId::Integer),
+        Value::from(DEFAULT_POLL_INTERVAL_SECS),
+    )?;
+    db.add_config_option(
+        QUERY_TIMEOUT,
+        "Seconds after which an unfinished Athena query is cancelled, 0 for no limit",
+        &LogicalType::new(LogicalTypeId::Integer),
+        Value::from(0),
+    )?;
+    db.add_config_option(
+        WORKGROUP,
+        "Athena workgroup queries are run in",
+        &LogicalType::new(LogicalTypeId::Varchar),
+        Value::from(DEFAULT_WORKGROUP),
+    )?;
+    db.add_config_option(
+        REGION,
+        "AWS region of Athena and Glue, empty to use the region of the environment",
+        &LogicalType::new(LogicalTypeId::Varchar),
+        Value::from(""),
+    )?;
+    db.add_config_option(
+        OUTPUT_LOCATION,
+        "S3 location Athena writes results to when athena_scan is not given one",
+        &LogicalType::new(LogicalTypeId::Varchar),
+        Value::from(""),
+    )?;
+    db.add_config_option(
+        REUSE_RESULTS_MAX_AGE,
+        "Minutes Athena may reuse the results of an identical query for, 0 to always run it",
+        &LogicalType::new(LogicalTypeId::Integer),
+        Value::from(0),
+    )?;
+    db.add_config_option(
+        LOG_LEVEL,
+        "Status messages logged: off, error, warn, info or debug",
+        &LogicalType::new(LogicalTypeId::Varchar),
+        Value::from("warn"),
+    )?;
+    db.add_config_option(
+        LOG_FILE,
+        "File status messages are appended to, empty for stderr",
+        &LogicalType::new(LogicalTypeId::Varchar),
+        Value::from(""),
+    )?;
+    db.add_config_option(
+        RETRY_MAX_ATTEMPTS,
+        "Attempts made at AWS calls that fail with throttling or transient errors",
+        &LogicalType::new(LogicalTypeId::Integer),
+        Value::from(DEFAULT_RETRY_MAX_ATTEMPTS),
+    )?;
+    db.add_config_option(
+        RETRY_BASE_DELAY,
+        "Milliseconds waited at most before the first retry, doubled for each one after it",
+        &LogicalType::new(LogicalTypeId::Integer),
+        Value::from(DEFAULT_RETRY_BASE_DELAY_MS),
+    )?;
+    db.add_config_option(
+        RETRY_MAX_DELAY,
+        "Most milliseconds waited before a retry",
+        &LogicalType::new(LogicalTypeId::Integer),
+        Value::from(DEFAULT_RETRY_MAX_DELAY_MS),
+    )?;
+    db.add_config_option(
+        CACHE_ENABLED,
+        "Whether athena_scan results are cached on local disk",
+        &LogicalType::new(LogicalTypeId::Boolean),
+        Value::from(false),
+    )?;
+    db.add_config_option(
+        CACHE_DIRECTORY,
+        "Directory cached results are stored in, empty for a temporary directory",
+        &LogicalType::new(LogicalTypeId::Varchar),
+        Value::from(""),
+    )?;
+    db.add_config_option(
+        CACHE_TTL,
+        "Seconds cached results are used for",
+        &LogicalType::new(LogicalTypeId::Integer),
+        Value::from(DEFAULT_CACHE_TTL_SECS),
+    )?;
+    db.add_config_option(
+        CACHE_MAX_SIZE,
+        "Megabytes of cached results kept before the oldest are removed",
+        &LogicalType::new(LogicalTypeId::Integer),
+        Value::from(DEFAULT_CACHE_MAX_SIZE_MB),
+    )?;
+    db.add_config_option(
+        MAX_BYTES_SCANNED,
+        "Most bytes an athena_scan is expected to scan, by table statistics, 0 for no limit",
+        &LogicalType::new(LogicalTypeId::Bigint),
+        Value::from(0i64),
+    )?;
+    db.add_config_option(
+        SESSION_MAX_BYTES_SCANNED,
+        "Most bytes the session's Athena queries may scan together, 0 for no limit",
+        &LogicalType::new(LogicalTypeId::Bigint),
+        Value::from(0i64),
+    )?;
+    db.add_config_option(
+        PRICE_PER_TB,
+        "US dollars Athena charges per TB scanned, for athena_session_cost",
+        &LogicalType::new(LogicalTypeId::Double),
+        Value::from(DEFAULT_PRICE_PER_TB),
+    )?;
+    Ok(())
+}
+
+/// The extension's options as set in the session that binds a scan.
+#[derive(Debug, Clone)]
+pub struct AthenaConfig {
+    pub maxrows: i32,
+    /// Longest wait between checks of whether a query has finished
+    pub poll_interval: Duration,
+    /// How long a query may run before it is cancelled
+    pub query_timeout: Option<Duration>,
+    pub workgroup: String,
+    pub region: Option<String>,
+    pub output_location: Option<String>,
+    /// Minutes Athena may answer a query with the results of an identical one
+    pub reuse_results_max_age: Option<i32>,
+    pub log: LogConfig,
+    pub retry: RetryPolicy,
+    pub cache: CacheConfig,
+    pub cost: CostConfig,
+    /// The database the scan is bound in, which the extension's own queries of
+    /// the cache and of `UNLOAD`ed files run in
+    database: Option<Arc<SharedDatabase>>,
+}
+
+impl AthenaConfig {
+    /// Read the options, and start logging as they say.
+    pub fn from_bind(bind_info: &BindInfo) -> Self {
+        let option = |name: &str| bind_info.config_option(name).filter(|v| !v.is_null());
+        let log_level = option(LOG_LEVEL).map(|v| LogLevel::parse(&v.to_varchar()));
+        let log = LogConfig {
+            level: match &log_level {
+                Some(Ok(level)) => *level,
+                _ => DEFAULT_LOG_LEVEL,
+            },
+            file: option(LOG_FILE)
+                .map(|v| v.to_varchar())
+                .filter(|file| !file.is_empty())
+                .map(PathBuf::from),
+        };
+        logging::configure(&log);
+        if let Some(Err(e)) = log_level {
+            warn!("{}", e);
+        }
+        let poll_interval =
+            option(POLL_INTERVAL).map_or(DEFAULT_POLL_INTERVAL_SECS, |v| v.to_i32());
+        Self {
+            maxrows: option(MAXROWS).map_or(DEFAULT_MAXROWS, |v| v.to_i32()),
+            poll_interval: Duration::from_secs(poll_interval.max(1) as u64),
+            query_timeout: option(QUERY_TIMEOUT)
+                .map(|v| v.to_i32())
+                .filter(|&secs| secs > 0)
+                .map(|secs| Duration::from_secs(secs as u64)),
+            workgroup: option(WORKGROUP)
+                .map(|v| v.to_varchar())
+                .filter(|workgroup| !workgroup.is_empty())
+                .unwrap_or_else(|| DEFAULT_WORKGROUP.to_string()),
+            region: option(REGION)
+                .map(|v| v.to_varchar())
+                .filter(|region| !region.is_empty()),
+            output_location: option(OUTPUT_LOCATION)
+                .map(|v| v.to_varchar())
+                .filter(|location| !location.is_empty()),
+            reuse_results_max_age: option(REUSE_RESULTS_MAX_AGE)
+                .map(|v| v.to_i32().min(MAX_REUSE_RESULTS_MAX_AGE))
+                .filter(|&minutes| minutes > 0),
+            log,
+            retry: RetryPolicy {
+                max_attempts: option(RETRY_MAX_ATTEMPTS)
+                    .map_or(DEFAULT_RETRY_MAX_ATTEMPTS, |v| v.to_i32())
+                    .max(1) as u32,
+                base_delay: Duration::from_millis(
+                    option(RETRY_BASE_DELAY)
+                        .map_or(DEFAULT_RETRY_BASE_DELAY_MS, |v| v.to_i32())
+                        .max(0) as u64,
+                ),
+                max_delay: Duration::from_millis(
+                    option(RETRY_MAX_DELAY)
+                        .map_or(DEFAULT_RETRY_MAX_DELAY_MS, |v| v.to_i32())
+                        .max(0) as u64,
+                ),
+            },
+            cache: CacheConfig {
+                enabled: option(CACHE_ENABLED).map_or(false, |v| v.to_bool()),
+                directory: option(CACHE_DIRECTORY)
+                    .map(|v| v.to_varchar())
+                    .filter(|directory| !directory.is_empty())
+                    .map_or_else(
+                        || std::env::temp_dir().join("duckdb-athena-cache"),
+                        PathBuf::from,
+                    ),
+                ttl: Duration::from_secs(
+                    option(CACHE_TTL)
+                        .map_or(DEFAULT_CACHE_TTL_SECS, |v| v.to_i32())
+                        .max(0) as u64,
+                ),
+                max_size: option(CACHE_MAX_SIZE)
+                    .map_or(DEFAULT_CACHE_MAX_SIZE_MB, |v| v.to_i32())
+                    .max(0) as u64
+                    * 1024
+                    * 1024,
+            },
+            cost: CostConfig {
+                max_bytes_scanned: option(MAX_BYTES_SCANNED)
+                    .map(|v| v.to_i64())
+                    .filter(|&bytes| bytes > 0)
+                    .map(|bytes| bytes as u64),
+                session_max_bytes_scanned: option(SESSION_MAX_BYTES_SCANNED)
+                    .map(|v| v.to_i64())
+                    .filter(|&bytes| bytes > 0)
+                    .map(|bytes| bytes as u64),
+                price_per_tb: option(PRICE_PER_TB).map_or(DEFAULT_PRICE_PER_TB, |v| v.to_f64()),
+            },
+            database: bind_info.database().map(Arc::new),
+        }
+    }
+
+    /// The database the scan is bound in.
+    pub fn database(&self) -> Result<&Database> {
+        self.database
+            .as_deref()
+            .map(|database| &**database)
+            .ok_or_else(|| {
+                Error::DuckDB(
+                    "the database can't be reached from a DuckDB version the extension \
+                     wasn't built for"
+                        .to_string(),
+                )
+            })
+    }
+
+    /// AWS configuration from the environment, in the configured region if there is one.
+    ///
+    /// The SDK's own retries are turned off, calls are retried by [RetryPolicy].
+    pub async fn aws_config(&self) -> SdkConfig {
+        let loader = aws_config::from_env().retry_config(RetryConfig::disabled());
+        match &self.region {
+            Some(region) => loader.region(Region::new(region.clone())).load().await,
+            None => loader.load().await,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Guarding against scans that would cost more than allowed, and keeping track
+//! of what the queries of the session scanned.
+//!
+//! The session is the process the extension was loaded into, queries of every
+//! connection count towards it.
+
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use duckdb_athena_rust::table_function::{BindInfo, InitInfo};
+use duckdb_athena_rust::vtab::{ScanState, VTab};
+use duckdb_athena_rust::{Connection, DataChunk, LogicalType, LogicalTypeId};
+
+use crate::config::AthenaConfig;
+use crate::logging::warn;
+
+/// Least Athena bills a query that scanned data for.
+const MIN_BILLED_BYTES: u64 = 10 * 1024 * 1024;
+
+const BYTES_PER_TB: f64 = 1024.0 * 1024.0 * 1024.0 * 1024.0;
+
+/// Limits on what queries may scan, and what scanning costs.
+#[derive(Debug, Clone)]
+pub struct CostConfig {
+    /// Most bytes a single scan is expected to scan
+    pub max_bytes_scanned: Option<u64>,
+    /// Most bytes all queries of the session may scan together
+    pub session_max_bytes_scanned: Option<u64>,
+    /// US dollars Athena charges per TB scanned
+    pub price_per_tb: f64,
+}
+
+impl CostConfig {
+    /// Whether a scan needs an estimate of its size to be checked.
+    pub fn is_limited(&self) -> bool {
+        self.max_bytes_scanned.is_some() || self.session_max_bytes_scanned.is_some()
+    }
+
+    /// Refuse to query `table` when it is expected to scan `estimate` bytes
+    /// and that is over either limit.
+    pub fn check(&self, table: &str, estimate: Option<u64>) -> Result<()> {
+        if let Some(max) = self.max_bytes_scanned {
+            match estimate {
+                Some(bytes) if bytes > max => {
+                    return Err(anyhow!(
+                        "scanning {} would read about {} bytes, more than athena_max_bytes_scanned ({})",
+                        table,
+                        bytes,
+                        max
+                    ))
+                }
+                Some(_) => {}
+                None => warn!(
+                    "Size of {} is unknown, athena_max_bytes_scanned can't be checked",
+                    table
+                ),
+            }
+        }
+        self.check_session(table, estimate)
+    }
+
+    /// Refuse to query `table` when scanning `estimate` bytes would take the
+    /// session over its limit. Every query adds to what the session scanned, so
+    /// this is checked again before each one.
+    pub fn check_session(&self, table: &str, estimate: Option<u64>) -> Result<()> {
+        let scanned = SESSION.lock().expect("session cost lock").bytes_scanned;
+        self.check_session_scanned(table, estimate, scanned)
+    }
+
+    fn check_session_scanned(
+        &self,
+        table: &str,
+        estimate: Option<u64>,
+        scanned: u64,
+    ) -> Result<()> {
+        match self.session_max_bytes_scanned {
+            Some(max) if scanned.saturating_add(estimate.unwrap_or_default()) > max => Err(anyhow!(
+                "scanning {} would take the session over athena_session_max_bytes_scanned ({}), {} bytes were scanned already",
+                table,
+                max,
+                scanned
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// US dollars Athena charges for a query that scans `bytes` bytes.
+    pub fn estimated_cost(&self, bytes: u64) -> f64 {
+        bytes.max(MIN_BILLED_BYTES) as f64 / BYTES_PER_TB * self.price_per_tb
+    }
+}
+
+/// What the queries of the session scanned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SessionCost {
+    queries: u64,
+    bytes_scanned: u64,
+    /// Bytes scanned, with each query rounded up to the least Athena bills for
+    billed_bytes: u64,
+}
+
+impl SessionCost {
+    fn add(&mut self, bytes_scanned: u64) {
+        self.queries += 1;
+        self.bytes_scanned += bytes_scanned;
+        // Queries answered without scanning, such as reused results, aren't billed
+        if bytes_scanned > 0 {
+            self.billed_bytes += bytes_scanned.max(MIN_BILLED_BYTES);
+        }
+    }
+}
+
+static SESSION: Mutex<SessionCost> = Mutex::new(SessionCost {
+    queries: 0,
+    bytes_scanned: 0,
+    billed_bytes: 0,
+});
+
+/// Count a query run by the extension that scanned `bytes_scanned` bytes.
+pub fn record(bytes_scanned: u64) {
+    SESSION
+        .lock()
+        .expect("session cost lock")
+        .add(bytes_scanned);
+}
+
+/// `athena_session_cost()`: the queries the session ran, the bytes they
+/// scanned and what that cost.
+struct SessionCostFunction;
+
+impl VTab for SessionCostFunction {
+    type BindData = CostConfig;
+    /// The totals, until they were returned
+    type InitData = Mutex<Option<SessionCost>>;
+    type LocalInitData = ();
+
+    fn bind(bind: &BindInfo) -> duckdb_athena_rust::Result<CostConfig> {
+        bind.add_result_column("queries", LogicalType::new(LogicalTypeId::Bigint));
+        bind.add_result_column(
+            "data_scanned_bytes",
+            LogicalType::new(LogicalTypeId::Bigint),
+        );
+        bind.add_result_column("billed_bytes", LogicalType::new(LogicalTypeId::Bigint));
+        bind.add_result_column("cost_usd", LogicalType::new(LogicalTypeId::Double));
+        Ok(AthenaConfig::from_bind(bind).cost)
+    }
+
+    fn init(_init: &InitInfo, _config: &CostConfig) -> duckdb_athena_rust::Result<Self::InitData> {
+        let session = *SESSION.lock().expect("session cost lock");
+        Ok(Mutex::new(Some(session)))
+    }
+
+    fn func(state: &mut ScanState<Self>, output: &mut DataChunk) -> duckdb_athena_rust::Result<()> {
+        match state.init_data.lock().expect("session cost lock").take() {
+            Some(session) => {
+                let cost =
+                    session.billed_bytes as f64 / BYTES_PER_TB * state.bind_data.price_per_tb;
+                output.flat_vector(0).set(0, session.queries as i64)?;
+                output.flat_vector(1).set(0, session.bytes_scanned as i64)?;
+                output.flat_vector(2).set(0, session.billed_bytes as i64)?;
+                output.flat_vector(3).set(0, cost)?;
+                output.set_len(1);
+            }
+            None => output.set_len(0),
+        }
+        Ok(())
+    }
+}
+
+pub fn register_session_cost(connection: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+    connection.register_vtab::<SessionCostFunction>("athena_session_cost")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(
+        max_bytes_scanned: Option<u64>,
+        session_max_bytes_scanned: Option<u64>,
+    ) -> CostConfig {
+        CostConfig {
+            max_bytes_scanned,
+            session_max_bytes_scanned,
+            price_per_tb: 5.0,
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let unlimited = limits(None, None);
+        assert!(!unlimited.is_limited());
+        assert!(unlimited.check("t", Some(u64::MAX)).is_ok());
+
+        let limited = limits(Some(1000), None);
+        assert!(limited.is_limited());
+        assert!(limited.check("t", Some(1000)).is_ok());
+        assert!(limited.check("t", Some(1001)).is_err());
+        // Tables of unknown size are let through with a warning
+        assert!(limited.check("t", None).is_ok());
+    }
+
+    #[test]
+    fn test_check_session() {
+        let limited = limits(None, Some(1000));
+        assert!(limited.is_limited());
+        assert!(limited.check_session_scanned("t", Some(400), 600).is_ok());
+        assert!(limited.check_session_scanned("t", Some(401), 600).is_err());
+        assert!(limited.check_session_scanned("t", None, 1000).is_ok());
+        assert!(limited.check_session_scanned("t", None, 1001).is_err());
+        assert!(limits(None, None)
+            .check_session_scanned("t", Some(u64::MAX / 2), u64::MAX / 2)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_estimated_cost() {
+        let config = limits(None, None);
+        assert_eq!(config.estimated_cost(BYTES_PER_TB as u64), 5.0);
+        assert_eq!(config.estimated_cost(BYTES_PER_TB as u64 / 2), 2.5);
+        // Small scans are billed as the least Athena bills for
+        assert_eq!(
+            config.estimated_cost(0),
+            config.estimated_cost(MIN_BILLED_BYTES)
+        );
+    }
+
+    #[test]
+    fn test_session_cost() {
+        let mut session = SessionCost::default();
+        session.add(0);
+        assert_eq!(
+            session,
+            SessionCost {
+                queries: 1,
+                bytes_scanned: 0,
+                billed_bytes: 0
+            }
+        );
+        session.add(1);
+        session.add(MIN_BILLED_BYTES * 2);
+        assert_eq!(
+            session,
+            SessionCost {
+                queries: 3,
+                bytes_scanned: MIN_BILLED_BYTES * 2 + 1,
+                billed_bytes: MIN_BILLED_BYTES * 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let before = *SESSION.lock().unwrap();
+        record(42);
+        let after = *SESSION.lock().unwrap();
+        assert_eq!(after.queries, before.queries + 1);
+        assert_eq!(after.bytes_scanned, before.bytes_scanned + 42);
+        assert_eq!(after.billed_bytes, before.billed_bytes + MIN_BILLED_BYTES);
+    }
+}
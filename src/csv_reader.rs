@@ -0,0 +1,251 @@
+use anyhow::{anyhow, Result};
+
+use crate::s3::RangedObjectReader;
+
+/// A single CSV record, `None` marking a NULL value.
+pub type Record = Vec<Option<String>>;
+
+/// Incremental parser for the CSV files Athena writes to its output location.
+///
+/// Athena quotes every value and writes NULL as an empty, unquoted field, so
+/// that is the only case that yields `None`.
+#[derive(Default)]
+pub struct CsvParser {
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl CsvParser {
+    /// Append more bytes of the file to the parse buffer.
+    pub fn push(&mut self, bytes: &[u8]) {
+        if self.position > 0 {
+            self.buffer.drain(..self.position);
+            self.position = 0;
+        }
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Parse the next complete record out of the buffer.
+    ///
+    /// Returns `None` if the buffer does not hold a full record yet. Once `eof`
+    /// is set, a trailing record without a line terminator is returned as well.
+    pub fn next_record(&mut self, eof: bool) -> Result<Option<Record>> {
+        let input = &self.buffer[self.position..];
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let mut record = Vec::new();
+        let mut field = Vec::new();
+        let mut quoted = false;
+        let mut in_quotes = false;
+        let mut idx = 0;
+
+        while idx < input.len() {
+            let byte = input[idx];
+            if in_quotes {
+                if byte == b'"' {
+                    match input.get(idx + 1) {
+                        Some(b'"') => {
+                            field.push(b'"');
+                            idx += 2;
+                            continue;
+                        }
+                        // Can't tell an escaped quote from a closing one yet
+                        None if !eof => return Ok(None),
+                        _ => in_quotes = false,
+                    }
+                } else {
+                    field.push(byte);
+                }
+                idx += 1;
+                continue;
+            }
+
+            match byte {
+                b'"' => {
+                    in_quotes = true;
+                    quoted = true;
+                }
+                b',' => record.push(finish_field(&mut field, &mut quoted)?),
+                b'\n' => {
+                    record.push(finish_field(&mut field, &mut quoted)?);
+                    self.position += idx + 1;
+                    return Ok(Some(record));
+                }
+                b'\r' => {}
+                _ => field.push(byte),
+            }
+            idx += 1;
+        }
+
+        if !eof {
+            return Ok(None);
+        }
+        if in_quotes {
+            return Err(anyhow!("unterminated quoted field at end of CSV"));
+        }
+
+        record.push(finish_field(&mut field, &mut quoted)?);
+        self.position = self.buffer.len();
+        Ok(Some(record))
+    }
+}
+
+fn finish_field(field: &mut Vec<u8>, quoted: &mut bool) -> Result<Option<String>> {
+    let value = if field.is_empty() && !*quoted {
+        None
+    } else {
+        Some(String::from_utf8(std::mem::take(field))?)
+    };
+    *quoted = false;
+    Ok(value)
+}
+
+/// Streams the records of an Athena CSV result file stored in S3.
+pub struct CsvResultReader {
+    reader: RangedObjectReader,
+    parser: CsvParser,
+    eof: bool,
+}
+
+impl CsvResultReader {
+    pub fn new(reader: RangedObjectReader) -> Self {
+        Self {
+            reader,
+            parser: CsvParser::default(),
+            eof: false,
+        }
+    }
+
+    pub async fn next_record(&mut self) -> Result<Option<Record>> {
+        loop {
+            if let Some(record) = self.parser.next_record(self.eof)? {
+                return Ok(Some(record));
+            }
+            if self.eof {
+                return Ok(None);
+            }
+            match self.reader.next_range().await? {
+                Some(bytes) => self.parser.push(&bytes),
+                None => self.eof = true,
+            }
+        }
+    }
+
+    /// Read up to `max` records, fewer only once the file is exhausted.
+    pub async fn next_records(&mut self, max: usize) -> Result<Vec<Record>> {
+        let mut records = Vec::with_capacity(max);
+        while records.len() < max {
+            match self.next_record().await? {
+                Some(record) => records.push(record),
+                None => break,
+            }
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_all(input: &[u8]) -> Vec<Record> {
+        let mut parser = CsvParser::default();
+        parser.push(input);
+        let mut records = Vec::new();
+        while let Some(record) = parser.next_record(true).unwrap() {
+            records.push(record);
+        }
+        records
+    }
+
+    fn values(values: &[Option<&str>]) -> Record {
+        values.iter().map(|v| v.map(str::to_string)).collect()
+    }
+
+    #[test]
+    fn test_quoted_fields() {
+        let records = parse_all(b"\"a\",\"b, c\"\n\"d\",\"e\nf\"\n");
+        assert_eq!(
+            records,
+            vec![
+                values(&[Some("a"), Some("b, c")]),
+                values(&[Some("d"), Some("e\nf")])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_doubled_quotes() {
+        let records = parse_all(b"\"say \"\"hi\"\"\",\"\"\"\"\n");
+        assert_eq!(records, vec![values(&[Some("say \"hi\""), Some("\"")])]);
+    }
+
+    #[test]
+    fn test_null_and_empty() {
+        let records = parse_all(b",\"\",\"x\",\n");
+        assert_eq!(records, vec![values(&[None, Some(""), Some("x"), None])]);
+    }
+
+    #[test]
+    fn test_crlf() {
+        let records = parse_all(b"\"a\",\"b\"\r\n\"c\",\r\n");
+        assert_eq!(
+            records,
+            vec![values(&[Some("a"), Some("b")]), values(&[Some("c"), None])]
+        );
+    }
+
+    #[test]
+    fn test_trailing_record_at_eof() {
+        let mut parser = CsvParser::default();
+        parser.push(b"\"a\",\"b\"");
+        assert_eq!(parser.next_record(false).unwrap(), None);
+        assert_eq!(
+            parser.next_record(true).unwrap(),
+            Some(values(&[Some("a"), Some("b")]))
+        );
+        assert_eq!(parser.next_record(true).unwrap(), None);
+    }
+
+    #[test]
+    fn test_unterminated_quote() {
+        let mut parser = CsvParser::default();
+        parser.push(b"\"a\",\"b");
+        assert!(parser.next_record(true).is_err());
+    }
+
+    #[test]
+    fn test_record_split_across_ranges() {
+        let mut parser = CsvParser::default();
+        parser.push(b"\"1\",\"a\"\n\"2\",\"b");
+        assert_eq!(
+            parser.next_record(false).unwrap(),
+            Some(values(&[Some("1"), Some("a")]))
+        );
+        assert_eq!(parser.next_record(false).unwrap(), None);
+
+        parser.push(b"c\"\n");
+        assert_eq!(
+            parser.next_record(false).unwrap(),
+            Some(values(&[Some("2"), Some("bc")]))
+        );
+        assert_eq!(parser.next_record(false).unwrap(), None);
+    }
+
+    #[test]
+    fn test_quote_split_across_ranges() {
+        let mut parser = CsvParser::default();
+        // Whether the quote ends the field depends on the next range
+        parser.push(b"\"a\"");
+        assert_eq!(parser.next_record(false).unwrap(), None);
+        parser.push(b"\"b\"\r");
+        assert_eq!(parser.next_record(false).unwrap(), None);
+        parser.push(b"\n");
+        assert_eq!(
+            parser.next_record(false).unwrap(),
+            Some(values(&[Some("a\"b")]))
+        );
+    }
+}
@@ -3,11 +3,30 @@ use duckdb_athena_rust::Database;
 use std::ffi::c_char;
 use tokio::runtime::Runtime;
 
+mod cache;
+mod config;
+mod cost;
+mod csv_reader;
 pub mod error;
+mod logging;
+mod macros;
+mod query_info;
+mod replacement_scan;
+mod retry;
+mod s3;
+mod scalar_function;
 mod table_function;
 mod types;
+mod unload;
 
-use crate::table_function::build_table_function_def;
+use crate::cache::register_cache_clear;
+use crate::config::register_options;
+use crate::cost::register_session_cost;
+use crate::macros::install_macros;
+use crate::query_info::register_query_info;
+use crate::replacement_scan::register_replacement_scan;
+use crate::scalar_function::build_scalar_function_defs;
+use crate::table_function::register_table_function;
 use duckdb_athena_rust::{_duckdb_database, duckdb_library_version};
 use error::Result;
 
@@ -26,9 +45,17 @@ pub unsafe extern "C" fn athena_init(db: *mut _duckdb_database) {
 
 unsafe fn init(db: *mut _duckdb_database) -> Result<()> {
     let db = Database::from(db);
-    let table_function = build_table_function_def();
+    register_options(&db)?;
     let connection = db.connect()?;
-    connection.register_table_function(table_function)?;
+    register_table_function(&connection)?;
+    register_cache_clear(&connection)?;
+    register_query_info(&connection)?;
+    register_session_cost(&connection)?;
+    register_replacement_scan(&db);
+    for scalar_function in build_scalar_function_defs() {
+        connection.register_scalar_function(scalar_function)?;
+    }
+    install_macros(&db);
     Ok(())
 }
 
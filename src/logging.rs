@@ -0,0 +1,248 @@
+//! Status messages of the extension.
+//!
+//! They go to stderr or a file rather than stdout, which DuckDB may be writing
+//! query results to. DuckDB 1.1 has no logging of its own for extensions to use.
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "error" => Ok(Self::Error),
+            "warn" | "warning" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            _ => Err(Error::DuckDB(format!(
+                "Invalid log level {value}, expected off, error, warn, info or debug"
+            ))),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+        }
+    }
+}
+
+/// What is logged, and where to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogConfig {
+    pub level: LogLevel,
+    /// File messages are appended to instead of stderr
+    pub file: Option<PathBuf>,
+}
+
+pub const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Warn;
+
+struct Logger {
+    config: LogConfig,
+    file: Option<File>,
+}
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    config: LogConfig {
+        level: DEFAULT_LOG_LEVEL,
+        file: None,
+    },
+    file: None,
+});
+
+impl Logger {
+    fn configure(&mut self, config: &LogConfig) {
+        if self.config == *config {
+            return;
+        }
+        self.file = config.file.as_ref().and_then(|path| {
+            match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    eprintln!(
+                        "[athena] WARN could not open log file {}: {}",
+                        path.display(),
+                        e
+                    );
+                    None
+                }
+            }
+        });
+        self.config = config.clone();
+    }
+
+    fn enabled(&self, level: LogLevel) -> bool {
+        level != LogLevel::Off && level <= self.config.level
+    }
+
+    fn log(&mut self, level: LogLevel, args: fmt::Arguments) {
+        if !self.enabled(level) {
+            return;
+        }
+        let line = format!("[athena] {} {}\n", level.name(), args);
+        // Failing to log must not fail the query
+        let _ = match &mut self.file {
+            Some(file) => file.write_all(line.as_bytes()),
+            None => std::io::stderr().write_all(line.as_bytes()),
+        };
+    }
+}
+
+/// Log with the options of the session that last bound a function. Messages are
+/// also written from threads that don't know which session they work for.
+pub fn configure(config: &LogConfig) {
+    LOGGER.lock().expect("logger lock").configure(config);
+}
+
+pub fn log(level: LogLevel, args: fmt::Arguments) {
+    LOGGER.lock().expect("logger lock").log(level, args);
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::logging::log($crate::logging::LogLevel::Error, format_args!($($arg)*))
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::logging::log($crate::logging::LogLevel::Warn, format_args!($($arg)*))
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::logging::log($crate::logging::LogLevel::Info, format_args!($($arg)*))
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::logging::log($crate::logging::LogLevel::Debug, format_args!($($arg)*))
+    };
+}
+
+// A macro named `warn` can't be re-exported as is, the name is ambiguous with
+// the built-in `#[warn]` attribute
+pub(crate) use {debug, error, info, log_warn as warn};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn logger() -> Logger {
+        Logger {
+            config: LogConfig {
+                level: DEFAULT_LOG_LEVEL,
+                file: None,
+            },
+            file: None,
+        }
+    }
+
+    /// A path in a fresh directory for a test to log to.
+    fn test_log_file(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "duckdb-athena-log-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory.join("athena.log")
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(LogLevel::parse("off").unwrap(), LogLevel::Off);
+        assert_eq!(LogLevel::parse("error").unwrap(), LogLevel::Error);
+        assert_eq!(LogLevel::parse("warn").unwrap(), LogLevel::Warn);
+        assert_eq!(LogLevel::parse("Warning").unwrap(), LogLevel::Warn);
+        assert_eq!(LogLevel::parse("INFO").unwrap(), LogLevel::Info);
+        assert_eq!(LogLevel::parse("debug").unwrap(), LogLevel::Debug);
+        assert!(LogLevel::parse("trace").is_err());
+        assert!(LogLevel::parse("").is_err());
+    }
+
+    #[test]
+    fn test_enabled() {
+        let mut logger = logger();
+        assert!(logger.enabled(LogLevel::Error));
+        assert!(logger.enabled(LogLevel::Warn));
+        assert!(!logger.enabled(LogLevel::Info));
+        assert!(!logger.enabled(LogLevel::Debug));
+
+        logger.config.level = LogLevel::Debug;
+        assert!(logger.enabled(LogLevel::Debug));
+        // Off is a level to set, not one to log at
+        assert!(!logger.enabled(LogLevel::Off));
+
+        logger.config.level = LogLevel::Off;
+        assert!(!logger.enabled(LogLevel::Error));
+    }
+
+    #[test]
+    fn test_log_to_file() {
+        let path = test_log_file("file");
+        let mut logger = logger();
+        logger.configure(&LogConfig {
+            level: LogLevel::Info,
+            file: Some(path.clone()),
+        });
+        logger.log(LogLevel::Info, format_args!("reading {} rows", 3));
+        logger.log(LogLevel::Debug, format_args!("not written"));
+        logger.log(LogLevel::Error, format_args!("failed"));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[athena] INFO reading 3 rows\n[athena] ERROR failed\n"
+        );
+
+        // Configuring the same file again appends to it
+        logger.configure(&LogConfig {
+            level: LogLevel::Warn,
+            file: Some(path.clone()),
+        });
+        logger.log(LogLevel::Warn, format_args!("again"));
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .ends_with("[athena] ERROR failed\n[athena] WARN again\n"));
+    }
+
+    #[test]
+    fn test_log_file_fallback() {
+        let path = test_log_file("fallback");
+        // A directory can't be opened as a file to append to
+        let directory = path.parent().unwrap().to_path_buf();
+        let config = LogConfig {
+            level: LogLevel::Debug,
+            file: Some(directory),
+        };
+        let mut logger = logger();
+        logger.file = Some(File::create(&path).unwrap());
+        logger.configure(&config);
+        // Messages go to stderr instead, at the configured level
+        assert!(logger.file.is_none());
+        assert_eq!(logger.config, config);
+        logger.log(LogLevel::Debug, format_args!("to stderr"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    }
+}
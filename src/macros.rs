@@ -0,0 +1,85 @@
+use duckdb_athena_rust::Database;
+
+use crate::logging::warn;
+
+/// SQL macros installed when the extension is loaded, as `(name, definition)`.
+const MACROS: &[(&str, &str)] = &[
+    (
+        "athena_table",
+        "(database, table_name, output_location := '') AS TABLE \
+         SELECT * FROM athena_scan('athena://' || database || '/' || table_name, output_location)",
+    ),
+    (
+        "athena_sample",
+        "(database, table_name, n := 100, output_location := '') AS TABLE \
+         SELECT * FROM athena_scan('athena://' || database || '/' || table_name, output_location, \
+         maxrows := n)",
+    ),
+];
+
+/// Register the helper macros on top of the functions registered by the extension.
+///
+/// They are kept in the system catalog rather than the database, so that
+/// loading the extension writes nothing to the user's database file, works in
+/// databases opened read-only, and leaves macros of the same name that users
+/// created alone. Failures are logged rather than failing the load, the
+/// functions themselves work all the same.
+pub fn install_macros(database: &Database) {
+    for (name, definition) in MACROS {
+        if let Err(e) = database.register_macro(&format!("CREATE MACRO {name}{definition}")) {
+            warn!("Could not create macro {}: {}", name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use duckdb_athena_rust::{Connection, Result};
+
+    fn ok<T>(result: Result<T>) -> T {
+        result.unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn macro_databases(connection: &Connection, name: &str) -> Vec<String> {
+        ok(connection.query(&format!(
+            "SELECT database_name FROM duckdb_functions() \
+             WHERE function_name = '{name}' ORDER BY database_name"
+        )))
+        .rows()
+        .iter()
+        .map(|row| row[0].to_varchar())
+        .collect()
+    }
+
+    #[test]
+    fn test_install_macros() {
+        let database = ok(Database::open_in_memory());
+        install_macros(&database);
+        // Installed twice, as when the extension is loaded again
+        install_macros(&database);
+
+        let connection = ok(database.connect());
+        for (name, _) in MACROS {
+            assert_eq!(macro_databases(&connection, name), vec!["system"]);
+        }
+        // Visible to connections opened later too
+        let other = ok(database.connect());
+        assert_eq!(macro_databases(&other, "athena_table"), vec!["system"]);
+    }
+
+    #[test]
+    fn test_user_macros_take_precedence() {
+        let database = ok(Database::open_in_memory());
+        let connection = ok(database.connect());
+        ok(connection.query("CREATE MACRO athena_sample() AS TABLE SELECT 42 AS answer"));
+        install_macros(&database);
+
+        let mut result = ok(connection.query("SELECT answer FROM athena_sample()"));
+        assert_eq!(result.rows()[0][0].to_i64(), 42);
+        assert_eq!(
+            macro_databases(&connection, "athena_sample"),
+            vec!["memory", "system"]
+        );
+    }
+}
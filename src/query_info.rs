@@ -0,0 +1,299 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use aws_sdk_athena::model::QueryExecution;
+use aws_sdk_athena::types::DateTime;
+use aws_sdk_athena::Client as AthenaClient;
+use duckdb_athena_rust::table_function::{BindInfo, InitInfo};
+use duckdb_athena_rust::vtab::{ScanState, VTab};
+use duckdb_athena_rust::{
+    duckdb_vector_size, Connection, DataChunk, FlatVector, LogicalType, LogicalTypeId, Timestamp,
+    VectorValue,
+};
+use futures::executor::block_on;
+
+use crate::config::AthenaConfig;
+use crate::retry::RetryPolicy;
+use crate::table_function::duckdb_error;
+
+/// Most query executions `BatchGetQueryExecution` takes at once.
+const BATCH_SIZE: usize = 50;
+
+const DEFAULT_MAX_QUERIES: i32 = 100;
+
+/// Columns describing a query execution, in output order.
+const COLUMNS: [(&str, LogicalTypeId); 13] = [
+    ("query_execution_id", LogicalTypeId::Varchar),
+    ("state", LogicalTypeId::Varchar),
+    ("state_change_reason", LogicalTypeId::Varchar),
+    ("workgroup", LogicalTypeId::Varchar),
+    ("query", LogicalTypeId::Varchar),
+    ("submitted_at", LogicalTypeId::Timestamp),
+    ("completed_at", LogicalTypeId::Timestamp),
+    ("data_scanned_bytes", LogicalTypeId::Bigint),
+    ("engine_execution_millis", LogicalTypeId::Bigint),
+    ("queue_millis", LogicalTypeId::Bigint),
+    ("planning_millis", LogicalTypeId::Bigint),
+    ("total_execution_millis", LogicalTypeId::Bigint),
+    ("output_location", LogicalTypeId::Varchar),
+];
+
+fn add_columns(bind_info: &BindInfo) {
+    for (name, type_id) in COLUMNS {
+        bind_info.add_result_column(name, LogicalType::new(type_id));
+    }
+}
+
+fn timestamp(time: &DateTime) -> Timestamp {
+    Timestamp(time.secs() * 1_000_000 + i64::from(time.subsec_nanos() / 1_000))
+}
+
+fn set_optional<T: VectorValue>(vector: &FlatVector, row: usize, value: Option<T>) -> Result<()> {
+    match value {
+        Some(value) => vector.set(row, value).map_err(|e| anyhow!(e.to_string())),
+        None => {
+            vector.set_null(row);
+            Ok(())
+        }
+    }
+}
+
+/// Write the details of `execution` into row `row` of `output`, in the order of [COLUMNS].
+fn write_execution(output: &DataChunk, row: usize, execution: &QueryExecution) -> Result<()> {
+    let status = execution.status();
+    let statistics = execution.statistics();
+    let strings = [
+        (0, execution.query_execution_id()),
+        (
+            1,
+            status.and_then(|s| s.state()).map(|state| state.as_str()),
+        ),
+        (2, status.and_then(|s| s.state_change_reason())),
+        (3, execution.work_group()),
+        (4, execution.query()),
+        (
+            12,
+            execution
+                .result_configuration()
+                .and_then(|rc| rc.output_location()),
+        ),
+    ];
+    let timestamps = [
+        (5, status.and_then(|s| s.submission_date_time())),
+        (6, status.and_then(|s| s.completion_date_time())),
+    ];
+    let bigints = [
+        (7, statistics.and_then(|s| s.data_scanned_in_bytes())),
+        (
+            8,
+            statistics.and_then(|s| s.engine_execution_time_in_millis()),
+        ),
+        (9, statistics.and_then(|s| s.query_queue_time_in_millis())),
+        (
+            10,
+            statistics.and_then(|s| s.query_planning_time_in_millis()),
+        ),
+        (
+            11,
+            statistics.and_then(|s| s.total_execution_time_in_millis()),
+        ),
+    ];
+
+    for (idx, value) in strings {
+        set_optional(&output.flat_vector(idx), row, value)?;
+    }
+    for (idx, value) in timestamps {
+        set_optional(&output.flat_vector(idx), row, value.map(timestamp))?;
+    }
+    for (idx, value) in bigints {
+        set_optional(&output.flat_vector(idx), row, value)?;
+    }
+    Ok(())
+}
+
+/// Write the next vector's worth of query executions into `output`.
+fn write_executions(
+    executions: &Mutex<VecDeque<QueryExecution>>,
+    output: &mut DataChunk,
+) -> Result<()> {
+    let capacity = unsafe { duckdb_vector_size() } as usize;
+    let mut executions = executions.lock().expect("query executions lock");
+    let count = capacity.min(executions.len());
+    for (row, execution) in executions.drain(..count).enumerate() {
+        write_execution(output, row, &execution)?;
+    }
+    output.set_len(count);
+    Ok(())
+}
+
+async fn get_query_execution(
+    client: &AthenaClient,
+    retry: &RetryPolicy,
+    id: String,
+) -> Result<QueryExecution> {
+    let resp = retry
+        .retry(|| {
+            client
+                .get_query_execution()
+                .query_execution_id(id.clone())
+                .send()
+        })
+        .await?;
+    resp.query_execution()
+        .cloned()
+        .ok_or_else(|| anyhow!("could not get query execution {}", id))
+}
+
+/// The most recent query executions of `workgroup`, newest first.
+async fn list_query_executions(
+    client: &AthenaClient,
+    retry: &RetryPolicy,
+    workgroup: &str,
+    max_queries: Option<usize>,
+) -> Result<Vec<QueryExecution>> {
+    let mut ids = Vec::new();
+    let mut next_token: Option<String> = None;
+    loop {
+        let page = retry
+            .retry(|| {
+                client
+                    .list_query_executions()
+                    .work_group(workgroup)
+                    .set_next_token(next_token.clone())
+                    .send()
+            })
+            .await?;
+        ids.extend_from_slice(page.query_execution_ids().unwrap_or_default());
+        if max_queries.map_or(false, |max| ids.len() >= max) {
+            break;
+        }
+        match page.next_token() {
+            Some(token) => next_token = Some(token.to_string()),
+            None => break,
+        }
+    }
+    if let Some(max) = max_queries {
+        ids.truncate(max);
+    }
+
+    let mut executions = HashMap::new();
+    for batch in ids.chunks(BATCH_SIZE) {
+        let resp = retry
+            .retry(|| {
+                client
+                    .batch_get_query_execution()
+                    .set_query_execution_ids(Some(batch.to_vec()))
+                    .send()
+            })
+            .await?;
+        for execution in resp.query_executions().unwrap_or_default() {
+            if let Some(id) = execution.query_execution_id() {
+                executions.insert(id.to_string(), execution.clone());
+            }
+        }
+    }
+    // Batches come back in no particular order
+    Ok(ids.iter().filter_map(|id| executions.remove(id)).collect())
+}
+
+/// `athena_query_status(query_execution_id)`: the state and statistics of a query.
+struct QueryStatus;
+
+impl VTab for QueryStatus {
+    type BindData = (AthenaConfig, String);
+    type InitData = Mutex<VecDeque<QueryExecution>>;
+    type LocalInitData = ();
+
+    fn bind(bind: &BindInfo) -> duckdb_athena_rust::Result<Self::BindData> {
+        add_columns(bind);
+        Ok((
+            AthenaConfig::from_bind(bind),
+            bind.parameter(0).to_varchar(),
+        ))
+    }
+
+    fn init(
+        _init: &InitInfo,
+        bind_data: &Self::BindData,
+    ) -> duckdb_athena_rust::Result<Self::InitData> {
+        let (athena_config, id) = bind_data;
+        let config = block_on(athena_config.aws_config());
+        let client = AthenaClient::new(&config);
+        let execution = crate::RUNTIME
+            .block_on(get_query_execution(
+                &client,
+                &athena_config.retry,
+                id.clone(),
+            ))
+            .map_err(duckdb_error)?;
+        Ok(Mutex::new(VecDeque::from([execution])))
+    }
+
+    fn func(state: &mut ScanState<Self>, output: &mut DataChunk) -> duckdb_athena_rust::Result<()> {
+        write_executions(state.init_data, output).map_err(duckdb_error)
+    }
+
+    fn parameters() -> Vec<LogicalType> {
+        vec![LogicalType::new(LogicalTypeId::Varchar)]
+    }
+}
+
+/// `athena_query_history()`: the most recent queries of a workgroup, the
+/// `athena_workgroup` one unless `workgroup=` is given.
+struct QueryHistory;
+
+impl VTab for QueryHistory {
+    /// The configuration, with the workgroup to list, and how many queries to list
+    type BindData = (AthenaConfig, Option<usize>);
+    type InitData = Mutex<VecDeque<QueryExecution>>;
+    type LocalInitData = ();
+
+    fn bind(bind: &BindInfo) -> duckdb_athena_rust::Result<Self::BindData> {
+        add_columns(bind);
+        let mut athena_config = AthenaConfig::from_bind(bind);
+        if let Some(workgroup) = bind.named_parameter("workgroup") {
+            athena_config.workgroup = workgroup.to_varchar();
+        }
+        let max_queries = bind
+            .named_parameter("max_queries")
+            .map_or(DEFAULT_MAX_QUERIES, |v| v.to_i32());
+        // Negative for all of them, like maxrows
+        let max_queries = usize::try_from(max_queries).ok();
+        Ok((athena_config, max_queries))
+    }
+
+    fn init(
+        _init: &InitInfo,
+        bind_data: &Self::BindData,
+    ) -> duckdb_athena_rust::Result<Self::InitData> {
+        let (athena_config, max_queries) = bind_data;
+        let config = block_on(athena_config.aws_config());
+        let client = AthenaClient::new(&config);
+        let executions = crate::RUNTIME
+            .block_on(list_query_executions(
+                &client,
+                &athena_config.retry,
+                &athena_config.workgroup,
+                *max_queries,
+            ))
+            .map_err(duckdb_error)?;
+        Ok(Mutex::new(executions.into()))
+    }
+
+    fn func(state: &mut ScanState<Self>, output: &mut DataChunk) -> duckdb_athena_rust::Result<()> {
+        write_executions(state.init_data, output).map_err(duckdb_error)
+    }
+
+    fn named_parameters() -> Vec<(&'static str, LogicalType)> {
+        vec![
+            ("workgroup", LogicalType::new(LogicalTypeId::Varchar)),
+            ("max_queries", LogicalType::new(LogicalTypeId::Integer)),
+        ]
+    }
+}
+
+pub fn register_query_info(connection: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+    connection.register_vtab::<QueryStatus>("athena_query_status")?;
+    connection.register_vtab::<QueryHistory>("athena_query_history")
+}
@@ -0,0 +1,201 @@
+use duckdb_athena_rust::{Database, Value};
+
+use crate::error::{Error, Result};
+use crate::types::quote_identifier;
+
+const URL_SCHEME: &str = "athena://";
+
+/// Name of the data catalog Athena uses for the Glue Data Catalog of the account.
+const DEFAULT_CATALOG: &str = "AwsDataCatalog";
+
+const DEFAULT_DATABASE: &str = "default";
+
+/// A table to scan, named either plainly (in the `default` database) or with an
+/// `athena://database/table?catalog=name&workgroup=name` URL, whose parts may be
+/// percent-encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AthenaTable {
+    pub catalog: Option<String>,
+    pub database: String,
+    pub table: String,
+    /// Workgroup to run the query in instead of the `athena_workgroup` option
+    pub workgroup: Option<String>,
+}
+
+impl AthenaTable {
+    pub fn parse(name: &str) -> Result<Self> {
+        let url = match name.strip_prefix(URL_SCHEME) {
+            Some(url) => url,
+            None => {
+                return Ok(Self {
+                    catalog: None,
+                    database: DEFAULT_DATABASE.to_string(),
+                    table: name.to_string(),
+                    workgroup: None,
+                })
+            }
+        };
+
+        let invalid = |reason: &str| Error::DuckDB(format!("Invalid Athena URL {name}: {reason}"));
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let (database, table) = path
+            .trim_end_matches('/')
+            .split_once('/')
+            .filter(|(database, table)| !database.is_empty() && !table.is_empty())
+            .ok_or_else(|| invalid("expected athena://database/table"))?;
+        if table.contains('/') {
+            return Err(invalid("expected athena://database/table"));
+        }
+
+        let decode = |part: &str| {
+            percent_decode(part).ok_or_else(|| invalid(&format!("invalid escape in '{part}'")))
+        };
+        let mut this = Self {
+            catalog: None,
+            database: decode(database)?,
+            table: decode(table)?,
+            workgroup: None,
+        };
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            match param.split_once('=') {
+                Some(("catalog", value)) if !value.is_empty() => {
+                    this.catalog = Some(decode(value)?)
+                }
+                Some(("workgroup", value)) if !value.is_empty() => {
+                    this.workgroup = Some(decode(value)?)
+                }
+                _ => return Err(invalid(&format!("unsupported parameter '{param}'"))),
+            }
+        }
+        Ok(this)
+    }
+
+    /// Whether the table is in the Glue Data Catalog of the account.
+    pub fn in_glue_catalog(&self) -> bool {
+        self.catalog.as_deref().map_or(true, |catalog| {
+            catalog.eq_ignore_ascii_case(DEFAULT_CATALOG)
+        })
+    }
+
+    /// The table as quoted in Athena SQL.
+    pub fn sql_name(&self) -> String {
+        let database = quote_identifier(&self.database);
+        let table = quote_identifier(&self.table);
+        match &self.catalog {
+            Some(catalog) => format!("{}.{}.{}", quote_identifier(catalog), database, table),
+            None => format!("{}.{}", database, table),
+        }
+    }
+}
+
+/// Decode the `%XX` escapes of a URL part, `None` if one is malformed or the
+/// result isn't UTF-8.
+fn percent_decode(part: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(part.len());
+    let mut rest = part.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Rewrite `SELECT * FROM 'athena://database/table'` into a call of `athena_scan`,
+/// writing results to the location set with `athena_output_location`.
+pub fn register_replacement_scan(db: &Database) {
+    db.add_replacement_scan(|info, table_name| {
+        if !table_name.starts_with(URL_SCHEME) {
+            return Ok(());
+        }
+        AthenaTable::parse(table_name)?;
+        info.set_function_name("athena_scan");
+        info.add_parameter(&Value::from(table_name));
+        info.add_parameter(&Value::from(""));
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(
+        catalog: Option<&str>,
+        database: &str,
+        table: &str,
+        workgroup: Option<&str>,
+    ) -> AthenaTable {
+        AthenaTable {
+            catalog: catalog.map(str::to_string),
+            database: database.to_string(),
+            table: table.to_string(),
+            workgroup: workgroup.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            AthenaTable::parse("orders").unwrap(),
+            table(None, "default", "orders", None)
+        );
+        assert_eq!(
+            AthenaTable::parse("athena://sales/orders").unwrap(),
+            table(None, "sales", "orders", None)
+        );
+        assert_eq!(
+            AthenaTable::parse("athena://sales/orders/").unwrap(),
+            table(None, "sales", "orders", None)
+        );
+    }
+
+    #[test]
+    fn test_parse_parameters() {
+        assert_eq!(
+            AthenaTable::parse("athena://sales/orders?catalog=hive&workgroup=etl").unwrap(),
+            table(Some("hive"), "sales", "orders", Some("etl"))
+        );
+        assert_eq!(
+            AthenaTable::parse("athena://sales/orders/?workgroup=etl&").unwrap(),
+            table(None, "sales", "orders", Some("etl"))
+        );
+        assert!(AthenaTable::parse("athena://sales/orders?region=us-east-1").is_err());
+        assert!(AthenaTable::parse("athena://sales/orders?catalog=").is_err());
+        assert!(AthenaTable::parse("athena://sales/orders?workgroup").is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid_path() {
+        assert!(AthenaTable::parse("athena://sales").is_err());
+        assert!(AthenaTable::parse("athena://sales/").is_err());
+        assert!(AthenaTable::parse("athena:///orders").is_err());
+        assert!(AthenaTable::parse("athena://sales/orders/2023").is_err());
+    }
+
+    #[test]
+    fn test_parse_percent_encoded() {
+        assert_eq!(
+            AthenaTable::parse("athena://my%20db/order%2Flines?workgroup=a%26b").unwrap(),
+            table(None, "my db", "order/lines", Some("a&b"))
+        );
+        assert!(AthenaTable::parse("athena://sales/orders%2").is_err());
+        assert!(AthenaTable::parse("athena://sales/orders%zz").is_err());
+        assert!(AthenaTable::parse("athena://sales/orders%+1").is_err());
+        assert!(AthenaTable::parse("athena://sales/orders%ff").is_err());
+    }
+
+    #[test]
+    fn test_in_glue_catalog() {
+        assert!(table(None, "sales", "orders", None).in_glue_catalog());
+        assert!(table(Some("awsdatacatalog"), "sales", "orders", None).in_glue_catalog());
+        assert!(!table(Some("hive"), "sales", "orders", None).in_glue_catalog());
+    }
+}
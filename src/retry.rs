@@ -0,0 +1,210 @@
+//! Retrying AWS calls that failed for reasons that are likely to pass, such as
+//! throttling, with exponential backoff and jitter.
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use aws_sdk_athena::types::SdkError;
+use aws_smithy_types::retry::ProvideErrorKind;
+
+use crate::logging::warn;
+
+/// Error codes worth retrying that services don't all mark as retryable.
+const RETRYABLE_CODES: [&str; 8] = [
+    "ThrottlingException",
+    "Throttling",
+    "TooManyRequestsException",
+    "RequestLimitExceeded",
+    "SlowDown",
+    "InternalServerException",
+    "InternalFailure",
+    "ServiceUnavailable",
+];
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts made in total, including the first one
+    pub max_attempts: u32,
+    /// Longest wait before the first retry, doubled for each one after it
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Make the call `call` builds, again while it fails with a retryable error
+    /// and attempts are left.
+    pub async fn retry<T, E, F, Fut>(&self, mut call: F) -> Result<T, SdkError<E>>
+    where
+        E: ProvideErrorKind,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, SdkError<E>>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Err(e) if attempt < self.max_attempts && is_retryable(&e) => {
+                    let delay = self.delay(attempt);
+                    warn!(
+                        "Retrying after {} in {} millis (attempt {} of {})",
+                        error_code(&e).unwrap_or("a transient error"),
+                        delay.as_millis(),
+                        attempt + 1,
+                        self.max_attempts
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// How long to wait before retrying for the `attempt`th time: a random part
+    /// of the exponentially growing, capped, delay, so that throttled clients
+    /// don't all come back at once.
+    fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(self.max_delay);
+        ceiling.mul_f64((random() >> 11) as f64 / (1u64 << 53) as f64)
+    }
+}
+
+fn is_retryable<E: ProvideErrorKind, R>(error: &SdkError<E, R>) -> bool {
+    match error {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => {
+            true
+        }
+        SdkError::ServiceError(context) => {
+            let err = context.err();
+            err.retryable_error_kind().is_some()
+                || err
+                    .code()
+                    .map_or(false, |code| RETRYABLE_CODES.contains(&code))
+        }
+        _ => false,
+    }
+}
+
+fn error_code<E: ProvideErrorKind, R>(error: &SdkError<E, R>) -> Option<&str> {
+    match error {
+        SdkError::ServiceError(context) => context.err().code(),
+        _ => None,
+    }
+}
+
+/// A random number, from the randomly keyed hasher of std rather than a crate.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// A token making retries of `StartQueryExecution` idempotent, so that a query
+/// isn't run twice when a response was lost.
+pub fn request_token() -> String {
+    format!("{:016x}{:016x}", random(), random())
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_smithy_types::retry::ErrorKind;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct ServiceError {
+        kind: Option<ErrorKind>,
+        code: Option<&'static str>,
+    }
+
+    impl ProvideErrorKind for ServiceError {
+        fn retryable_error_kind(&self) -> Option<ErrorKind> {
+            self.kind
+        }
+
+        fn code(&self) -> Option<&str> {
+            self.code
+        }
+    }
+
+    fn service_error(
+        kind: Option<ErrorKind>,
+        code: Option<&'static str>,
+    ) -> SdkError<ServiceError, ()> {
+        SdkError::service_error(ServiceError { kind, code }, ())
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(is_retryable(&SdkError::<ServiceError, ()>::timeout_error(
+            "timed out"
+        )));
+        assert!(!is_retryable(
+            &SdkError::<ServiceError, ()>::construction_failure("invalid request")
+        ));
+
+        assert!(is_retryable(&service_error(
+            Some(ErrorKind::ThrottlingError),
+            None
+        )));
+        assert!(is_retryable(&service_error(
+            None,
+            Some("TooManyRequestsException")
+        )));
+        assert!(is_retryable(&service_error(None, Some("SlowDown"))));
+        assert!(!is_retryable(&service_error(
+            None,
+            Some("InvalidRequestException")
+        )));
+        assert!(!is_retryable(&service_error(None, None)));
+    }
+
+    #[test]
+    fn test_error_code() {
+        assert_eq!(
+            error_code(&service_error(None, Some("ThrottlingException"))),
+            Some("ThrottlingException")
+        );
+        assert_eq!(
+            error_code(&SdkError::<ServiceError, ()>::timeout_error("timed out")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+        for _ in 0..100 {
+            assert!(policy.delay(1) <= Duration::from_millis(100));
+            assert!(policy.delay(3) <= Duration::from_millis(400));
+            assert!(policy.delay(5) <= Duration::from_secs(1));
+            // Capped rather than overflowing
+            assert!(policy.delay(1000) <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn test_delay_is_jittered() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(1),
+        };
+        let delays: Vec<Duration> = (0..20).map(|_| policy.delay(1)).collect();
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+    }
+
+    #[test]
+    fn test_request_token() {
+        let token = request_token();
+        assert_eq!(token.len(), 32);
+        assert!(token.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_ne!(token, request_token());
+    }
+}
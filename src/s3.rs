@@ -0,0 +1,226 @@
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use aws_sdk_s3::model::{Delete, ObjectIdentifier};
+use aws_sdk_s3::Client as S3Client;
+
+use crate::retry::RetryPolicy;
+
+/// Bucket and key of an `s3://bucket/key` URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Uri {
+    pub bucket: String,
+    pub key: String,
+}
+
+impl S3Uri {
+    pub fn parse(uri: &str) -> Result<Self> {
+        let path = uri
+            .strip_prefix("s3://")
+            .ok_or_else(|| anyhow!("not an S3 URI: {}", uri))?;
+        let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+        if bucket.is_empty() {
+            return Err(anyhow!("missing bucket in S3 URI: {}", uri));
+        }
+
+        Ok(Self {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for S3Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.key)
+    }
+}
+
+/// List the objects stored below `prefix`.
+pub async fn list_objects(
+    client: &S3Client,
+    retry: &RetryPolicy,
+    prefix: &S3Uri,
+) -> Result<Vec<S3Uri>> {
+    let mut objects = Vec::new();
+    let mut continuation_token: Option<String> = None;
+
+    loop {
+        let resp = retry
+            .retry(|| {
+                client
+                    .list_objects_v2()
+                    .bucket(&prefix.bucket)
+                    .prefix(&prefix.key)
+                    .set_continuation_token(continuation_token.clone())
+                    .send()
+            })
+            .await?;
+
+        for object in resp.contents().unwrap_or_default() {
+            match object.key() {
+                Some(key) if !key.ends_with('/') => objects.push(S3Uri {
+                    bucket: prefix.bucket.clone(),
+                    key: key.to_string(),
+                }),
+                _ => {}
+            }
+        }
+
+        match resp.next_continuation_token() {
+            Some(token) => continuation_token = Some(token.to_string()),
+            None => break,
+        }
+    }
+
+    Ok(objects)
+}
+
+/// Delete objects, batched by the 1000 keys S3 accepts per request.
+pub async fn delete_objects(
+    client: &S3Client,
+    retry: &RetryPolicy,
+    objects: &[S3Uri],
+) -> Result<()> {
+    for batch in objects.chunks(1000) {
+        let identifiers = batch
+            .iter()
+            .map(|object| ObjectIdentifier::builder().key(&object.key).build())
+            .collect();
+        let delete = Delete::builder()
+            .set_objects(Some(identifiers))
+            .quiet(true)
+            .build();
+
+        retry
+            .retry(|| {
+                client
+                    .delete_objects()
+                    .bucket(&batch[0].bucket)
+                    .delete(delete.clone())
+                    .send()
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Download an object into a local file.
+pub async fn download_object(
+    client: &S3Client,
+    retry: &RetryPolicy,
+    object: &S3Uri,
+    path: &Path,
+) -> Result<()> {
+    let resp = retry
+        .retry(|| {
+            client
+                .get_object()
+                .bucket(&object.bucket)
+                .key(&object.key)
+                .send()
+        })
+        .await?;
+    let bytes = resp.body.collect().await?.into_bytes();
+    tokio::fs::write(path, &bytes).await?;
+    Ok(())
+}
+
+/// Reads an S3 object front to back using ranged GETs, so large result files
+/// never have to be held in memory at once.
+pub struct RangedObjectReader {
+    client: S3Client,
+    retry: RetryPolicy,
+    location: S3Uri,
+    size: u64,
+    offset: u64,
+    range_size: u64,
+}
+
+impl RangedObjectReader {
+    pub async fn new(
+        client: S3Client,
+        retry: RetryPolicy,
+        location: S3Uri,
+        range_size: u64,
+    ) -> Result<Self> {
+        let head = retry
+            .retry(|| {
+                client
+                    .head_object()
+                    .bucket(&location.bucket)
+                    .key(&location.key)
+                    .send()
+            })
+            .await?;
+
+        Ok(Self {
+            client,
+            retry,
+            location,
+            size: head.content_length().max(0) as u64,
+            offset: 0,
+            range_size,
+        })
+    }
+
+    /// Fetch the next range of the object, or `None` once everything has been read.
+    pub async fn next_range(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.offset >= self.size {
+            return Ok(None);
+        }
+
+        let end = (self.offset + self.range_size).min(self.size) - 1;
+        let range = format!("bytes={}-{}", self.offset, end);
+        let resp = self
+            .retry
+            .retry(|| {
+                self.client
+                    .get_object()
+                    .bucket(&self.location.bucket)
+                    .key(&self.location.key)
+                    .range(&range)
+                    .send()
+            })
+            .await?;
+        let bytes = resp.body.collect().await?.into_bytes();
+
+        self.offset = end + 1;
+        Ok(Some(bytes.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let uri = S3Uri::parse("s3://bucket/path/to/object.csv").unwrap();
+        assert_eq!(uri.bucket, "bucket");
+        assert_eq!(uri.key, "path/to/object.csv");
+        assert_eq!(uri.to_string(), "s3://bucket/path/to/object.csv");
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        let uri = S3Uri::parse("s3://bucket/results/").unwrap();
+        assert_eq!(uri.key, "results/");
+
+        let uri = S3Uri::parse("s3://bucket").unwrap();
+        assert_eq!(uri.bucket, "bucket");
+        assert_eq!(uri.key, "");
+        let uri = S3Uri::parse("s3://bucket/").unwrap();
+        assert_eq!(uri.key, "");
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(S3Uri::parse("bucket/key").is_err());
+        assert!(S3Uri::parse("https://bucket/key").is_err());
+        assert!(S3Uri::parse("s3://").is_err());
+        assert!(S3Uri::parse("s3:///key").is_err());
+    }
+}
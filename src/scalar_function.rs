@@ -0,0 +1,132 @@
+use duckdb_athena_rust::scalar_function::ScalarFunction;
+use duckdb_athena_rust::{DataChunk, FlatVector, Inserter, LogicalType, LogicalTypeId, Result};
+
+use crate::types::{duckdb_type_name, map_type};
+
+/// `athena_duckdb_type(athena_type)`: the DuckDB type an Athena/Presto type is read as.
+fn athena_duckdb_type(input: &DataChunk, output: &mut FlatVector) -> Result<()> {
+    let athena_types = input.flat_vector(0);
+    for row in 0..input.len() {
+        if athena_types.is_null(row) {
+            output.set_null(row);
+            continue;
+        }
+        let athena_type = athena_types.get_string(row).trim().to_lowercase();
+        output.insert(row, duckdb_type_name(map_type(athena_type)?));
+    }
+    Ok(())
+}
+
+/// `athena_result_location(output_location, query_execution_id)`: the S3 URI of
+/// the CSV file Athena writes the results of a query to.
+fn athena_result_location(input: &DataChunk, output: &mut FlatVector) -> Result<()> {
+    let output_locations = input.flat_vector(0);
+    let query_execution_ids = input.flat_vector(1);
+    for row in 0..input.len() {
+        if output_locations.is_null(row) || query_execution_ids.is_null(row) {
+            output.set_null(row);
+            continue;
+        }
+        let location = format!(
+            "{}/{}.csv",
+            output_locations.get_string(row).trim_end_matches('/'),
+            query_execution_ids.get_string(row)
+        );
+        output.insert(row, location.as_str());
+    }
+    Ok(())
+}
+
+pub fn build_scalar_function_defs() -> Vec<ScalarFunction> {
+    let varchar = LogicalType::new(LogicalTypeId::Varchar);
+
+    let duckdb_type = ScalarFunction::new("athena_duckdb_type");
+    duckdb_type.add_parameter(&varchar);
+    duckdb_type.set_return_type(&varchar);
+    duckdb_type.set_function(athena_duckdb_type);
+
+    let result_location = ScalarFunction::new("athena_result_location");
+    result_location.add_parameter(&varchar);
+    result_location.add_parameter(&varchar);
+    result_location.set_return_type(&varchar);
+    result_location.set_function(athena_result_location);
+
+    vec![duckdb_type, result_location]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use duckdb_athena_rust::{Connection, Database, Value};
+
+    fn ok<T>(result: Result<T>) -> T {
+        result.unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn connect() -> Connection {
+        let connection = ok(ok(Database::open_in_memory()).connect());
+        for function in build_scalar_function_defs() {
+            connection.register_scalar_function(function).unwrap();
+        }
+        connection
+    }
+
+    fn column(connection: &Connection, sql: &str) -> Vec<Option<String>> {
+        ok(connection.query(sql))
+            .rows()
+            .iter()
+            .map(|row| {
+                Some(&row[0])
+                    .filter(|v| !v.is_null())
+                    .map(Value::to_varchar)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_athena_duckdb_type() {
+        let connection = connect();
+        // Strings of up to 12 bytes are inlined, longer ones are read through a pointer
+        let types = column(
+            &connection,
+            "SELECT athena_duckdb_type(t) FROM (VALUES ('bigint'), ('  Date '), \
+             ('decimal(38, 10)'), ('map<varchar,array<bigint>>'), (NULL)) v(t)",
+        );
+        assert_eq!(
+            types,
+            vec![
+                Some("BIGINT".to_string()),
+                Some("DATE".to_string()),
+                Some("DECIMAL".to_string()),
+                Some("MAP".to_string()),
+                None,
+            ]
+        );
+        assert!(connection
+            .query("SELECT athena_duckdb_type('interval year to month')")
+            .is_err());
+    }
+
+    #[test]
+    fn test_athena_result_location() {
+        let connection = connect();
+        let locations = column(
+            &connection,
+            "SELECT athena_result_location(l, id) FROM (VALUES \
+             ('s3://b/', 'abc'), \
+             ('s3://a-much-longer-bucket/results', '0d5e8f4c-7a3b-4e2a-9b1d-6c8f0a2e4b7d'), \
+             ('s3://b', NULL)) v(l, id)",
+        );
+        assert_eq!(
+            locations,
+            vec![
+                Some("s3://b/abc.csv".to_string()),
+                Some(
+                    "s3://a-much-longer-bucket/results/0d5e8f4c-7a3b-4e2a-9b1d-6c8f0a2e4b7d.csv"
+                        .to_string()
+                ),
+                None,
+            ]
+        );
+    }
+}
@@ -1,208 +1,620 @@
 use anyhow::{anyhow, Result};
-use aws_sdk_athena::model::ResultSetMetadata;
+use aws_config::SdkConfig;
+use aws_sdk_athena::model::{ColumnInfo, ResultSetMetadata};
 use aws_sdk_athena::types::SdkError;
 use aws_sdk_athena::{error::GetQueryResultsError, model::Row};
-use aws_sdk_glue::model::Logical;
-use futures::{executor::block_on, Stream};
-use std::os::fd::IntoRawFd;
-use std::ptr::null;
-use std::{
-    ffi::{c_void, CStr, CString},
-    os::raw::c_char,
-    pin::Pin,
-    task::{Context, Poll},
-    thread,
-};
+use futures::executor::block_on;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use aws_sdk_athena::{
     model::{
         QueryExecutionState::{self, *},
-        ResultConfiguration, ResultSet,
+        ResultConfiguration, ResultReuseByAgeConfiguration, ResultReuseConfiguration, ResultSet,
     },
     output::{GetQueryExecutionOutput, GetQueryResultsOutput},
-    paginator::GetQueryResultsPaginator,
     Client as AthenaClient,
 };
 use aws_sdk_glue::Client as GlueClient;
-use duckdb_athena_rust::table_function::{BindInfo, InitInfo, TableFunction};
-use duckdb_athena_rust::{
-    duckdb_bind_info, duckdb_data_chunk, duckdb_free, duckdb_function_info, duckdb_init_info,
-    malloc_struct,
+use aws_sdk_s3::Client as S3Client;
+use duckdb_athena_rust::duckdb_vector_size;
+use duckdb_athena_rust::table_function::{BindInfo, InitInfo};
+use duckdb_athena_rust::vtab::{ScanState, VTab};
+use duckdb_athena_rust::{Connection, DataChunk, LogicalType, LogicalTypeId};
+
+use tokio::sync::mpsc;
+
+use crate::cache::{cache_key, CacheWriter, CachedResults};
+use crate::config::{AthenaConfig, MAX_REUSE_RESULTS_MAX_AGE};
+use crate::cost::{self, CostConfig};
+use crate::csv_reader::{CsvResultReader, Record};
+use crate::logging::{debug, error, info, warn};
+use crate::replacement_scan::AthenaTable;
+use crate::retry::{request_token, RetryPolicy};
+use crate::s3::{RangedObjectReader, S3Uri};
+use crate::types::{
+    duckdb_type_sql, enum_dictionary, map_logical_type, map_type, populate_column,
+    quote_identifier, readable_from_text, ColumnType, EnumDictionary,
 };
-use duckdb_athena_rust::{DataChunk, FunctionInfo, LogicalType, LogicalTypeId};
+use crate::unload::{ParquetFiles, ParquetReader, UnloadPrefix};
 
-use tokio::{runtime::Runtime, time::Duration};
+/// How query results are read back from Athena once the query has finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FetchMode {
+    /// Page through `GetQueryResults`, 1000 rows at a time.
+    Api,
+    /// Stream the CSV file Athena wrote to the output location straight from S3.
+    Csv,
+    /// `UNLOAD` the results as Parquet to a temporary S3 prefix and read those files.
+    Unload,
+}
 
-use crate::types::{map_type, populate_column};
+impl FetchMode {
+    fn parse(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "api" => Ok(Self::Api),
+            "csv" => Ok(Self::Csv),
+            "unload" => Ok(Self::Unload),
+            _ => Err(anyhow!(
+                "Unsupported fetch_mode '{}', expected 'api', 'csv' or 'unload'",
+                value
+            )),
+        }
+    }
+}
 
-#[repr(C)]
 struct ScanBindData {
-    /// Athena table name and query result output location
-    tablename: *mut c_char,
-    output_location: *mut c_char,
+    /// Athena table and query result output location
+    table: AthenaTable,
+    output_location: String,
     limit: i32,
+    fetch_mode: FetchMode,
+    /// Number of `GetQueryResults` pages to download ahead of the scan
+    prefetch_pages: i32,
+    /// Rows per `GetQueryResults` page, `None` leaving it to Athena
+    page_size: Option<i32>,
+    /// Whether to delete the files written by `UNLOAD` once they have been read
+    unload_cleanup: bool,
+    /// Dictionaries of the columns read as `ENUM`, by column index
+    enum_columns: EnumColumns,
+    /// The extension's options in the binding session
+    config: AthenaConfig,
+    /// When Glue last saw the table change, part of the cache key
+    update_time: Option<i64>,
+    /// AWS region and profile the table was looked up in, part of the cache key
+    aws_scope: [String; 2],
+    /// Definitions of the result columns, for the table results are cached in.
+    /// Empty for dry runs when they depend on dictionaries that aren't cached.
+    cache_columns: Vec<String>,
+    /// Rows the scan is expected to return, if known
+    estimated_rows: Option<u64>,
+    /// Size of the table's data in bytes, if known
+    table_size: Option<u64>,
+    /// Whether to return the query, its estimate and Athena's plans for it
+    /// instead of running it
+    dry_run: bool,
 }
 
-const DEFAULT_LIMIT: i32 = 10000;
+const DEFAULT_PREFETCH_PAGES: i32 = 2;
 
-impl ScanBindData {
-    fn new(tablename: &str, output_location: &str) -> Self {
-        Self {
-            tablename: CString::new(tablename).expect("Table name").into_raw(),
-            output_location: CString::new(output_location)
-                .expect("S3 output location")
-                .into_raw(),
-            limit: DEFAULT_LIMIT as i32,
-        }
+/// Wait before the first check of whether a query has finished, doubled after
+/// each check up to the `athena_poll_interval` option.
+const INITIAL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Share of the progress bar, in percent, taken by running the query rather
+/// than reading its results.
+const EXECUTION_SHARE: f64 = 50.0;
+
+/// Largest `MaxResults` accepted by `GetQueryResults`.
+const MAX_PAGE_SIZE: i32 = 1000;
+
+/// Most values of an `ENUM` dictionary, columns with more are read as `VARCHAR`.
+const MAX_ENUM_VALUES: usize = 1000;
+
+/// Glue column parameter that, set to `true`, reads a string column as `ENUM`.
+const ENUM_COLUMN_PARAMETER: &str = "duckdb.enum";
+
+/// Dictionaries of the columns read as `ENUM`, by column index.
+type EnumColumns = HashMap<usize, Arc<EnumDictionary>>;
+
+/// Size of each ranged GET when streaming CSV results from S3.
+const CSV_RANGE_SIZE: u64 = 8 * 1024 * 1024;
+
+type ResultPage = Result<GetQueryResultsOutput, SdkError<GetQueryResultsError>>;
+
+/// `GetQueryResults` pages downloaded ahead of DuckDB asking for them.
+///
+/// A background task on the shared runtime keeps up to `depth` pages buffered,
+/// so fetching the next page overlaps with DuckDB consuming the current one.
+struct PagePrefetcher {
+    pages: mpsc::Receiver<ResultPage>,
+}
+
+impl PagePrefetcher {
+    fn spawn(
+        client: AthenaClient,
+        retry: RetryPolicy,
+        query_execution_id: String,
+        page_size: Option<i32>,
+        depth: usize,
+    ) -> Self {
+        let (tx, pages) = mpsc::channel(depth.max(1));
+        crate::RUNTIME.spawn(async move {
+            let mut next_token = None;
+            loop {
+                // Retries ask for the page after the last one received again
+                let page = get_results_page(
+                    &client,
+                    &retry,
+                    &query_execution_id,
+                    page_size,
+                    next_token.take(),
+                )
+                .await;
+                let failed = page.is_err();
+                next_token = page
+                    .as_ref()
+                    .ok()
+                    .and_then(|page| page.next_token())
+                    .map(str::to_string);
+                // Stop once the scan is gone or there are no pages left to get
+                if tx.send(page).await.is_err() || failed || next_token.is_none() {
+                    break;
+                }
+            }
+        });
+
+        Self { pages }
+    }
+
+    /// Wait for the next page, `None` once all pages were handed out.
+    fn next_page(&mut self) -> Option<ResultPage> {
+        crate::RUNTIME.block_on(self.pages.recv())
     }
 }
 
-/// Drop the ScanBindData from C.
-///
-/// # Safety
-unsafe extern "C" fn drop_scan_bind_data_c(v: *mut c_void) {
-    let actual = v.cast::<ScanBindData>();
-    drop(CString::from_raw((*actual).tablename.cast()));
-    drop(CString::from_raw((*actual).output_location.cast()));
-    drop((*actual).limit);
-    duckdb_free(v);
+/// Athena CSV results along with the DuckDB types of their columns.
+struct CsvResults {
+    reader: CsvResultReader,
+    column_types: Vec<ColumnType>,
+    /// Whether the reader has run out of rows
+    done: bool,
 }
 
-struct ResultStream {
-    stream:
-        Pin<Box<dyn Stream<Item = Result<GetQueryResultsOutput, SdkError<GetQueryResultsError>>>>>,
+/// `GetQueryResults` pages on their way into DuckDB chunks.
+struct PagedResults {
+    pages: PagePrefetcher,
+    cursor: RowCursor,
+    pagination_index: u32,
+    done: bool,
 }
 
-impl ResultStream {
-    pub fn new(
-        stream: Box<
-            dyn Stream<Item = Result<GetQueryResultsOutput, SdkError<GetQueryResultsError>>>,
-        >,
-    ) -> Self {
+impl PagedResults {
+    fn new(pages: PagePrefetcher, cursor: RowCursor) -> Self {
         Self {
-            stream: stream.into(),
+            pages,
+            cursor,
+            pagination_index: 0,
+            done: false,
         }
     }
 }
 
-impl Stream for ResultStream {
-    type Item = Result<GetQueryResultsOutput, SdkError<GetQueryResultsError>>;
+/// Where a scan reads its results from.
+///
+/// Parquet files are the only source scanned by more than one thread and
+/// synchronize internally; the others are only ever locked by a single thread.
+enum ResultSource {
+    Paginator(Mutex<PagedResults>),
+    Csv(Mutex<CsvResults>),
+    Parquet(ParquetFiles),
+    Cached(Mutex<CachedResults>),
+    /// The lines of a dry run
+    Plan(Mutex<RowCursor>),
+}
 
-    // https://stackoverflow.com/questions/72926989/how-to-implement-trait-futuresstreamstream was ridiculously helpful
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match self.get_mut().stream.as_mut().poll_next(cx) {
-            Poll::Pending => Poll::Pending,
-            Poll::Ready(v) => {
-                // Do what you need to do with v here.
-                Poll::Ready(v)
+impl ResultSource {
+    /// Whether all of the results were read, rather than the scan stopping early.
+    fn is_exhausted(&self) -> bool {
+        match self {
+            Self::Paginator(paged) => {
+                let paged = paged.lock().expect("result pages lock");
+                paged.done && paged.cursor.len() == 0
             }
+            Self::Csv(csv) => csv.lock().expect("CSV results lock").done,
+            Self::Parquet(files) => files.is_finished(),
+            Self::Cached(_) => true,
+            Self::Plan(plan) => plan.lock().expect("plan lock").len() == 0,
         }
     }
 }
-#[repr(C)]
+
+/// A query started by the init of a scan. The first thread to scan waits for
+/// it and opens its results, so that DuckDB shows progress in the meantime.
+struct PendingQuery {
+    client: AthenaClient,
+    sdk_config: SdkConfig,
+    query_execution_id: String,
+    unload_prefix: Option<UnloadPrefix>,
+}
+
+/// How far a scan has got, for DuckDB's progress bar.
+#[derive(Default)]
+struct ScanProgress {
+    /// Bytes the query had scanned as of the last poll
+    bytes_scanned: AtomicU64,
+    /// Rows handed to DuckDB
+    rows_read: AtomicU64,
+}
+
+/// The init data of a scan.
 struct ScanInitData {
-    stream: *mut ResultStream,
-    pagination_index: u32,
-    done: bool,
+    /// The results, once the query has finished
+    source: OnceLock<ResultSource>,
+    /// The query, until its results are opened
+    pending: Mutex<Option<PendingQuery>>,
+    progress: ScanProgress,
+    /// Copies the results into the local cache, when enabled and they weren't there
+    cache_writer: Mutex<Option<CacheWriter>>,
 }
 
 impl ScanInitData {
-    fn new(stream: Box<ResultStream>) -> Self {
+    /// The results of the scan, waiting for the query to finish if no other
+    /// thread has yet.
+    fn source(&self, bind_data: &ScanBindData) -> Result<&ResultSource> {
+        if let Some(source) = self.source.get() {
+            return Ok(source);
+        }
+        let mut pending = self.pending.lock().expect("pending query lock");
+        if let Some(source) = self.source.get() {
+            return Ok(source);
+        }
+        // Gone if another thread failed to open the results
+        let query = pending
+            .take()
+            .ok_or_else(|| anyhow!("Athena query failed"))?;
+        let source = open_results(bind_data, query, &self.progress)?;
+        Ok(self.source.get_or_init(|| source))
+    }
+
+    /// Percentage of the scan done. Running the query counts for the
+    /// [EXECUTION_SHARE] and reading the results for the rest.
+    fn progress(&self, bind_data: &ScanBindData) -> f64 {
+        let Some(source) = self.source.get() else {
+            // Athena doesn't say how much a query will scan, so assume all of the table
+            let scanned = self.progress.bytes_scanned.load(Ordering::Relaxed);
+            return EXECUTION_SHARE * fraction(scanned, bind_data.table_size);
+        };
+        let read = match source {
+            ResultSource::Parquet(files) => files.fraction_finished(),
+            _ => fraction(
+                self.progress.rows_read.load(Ordering::Relaxed),
+                bind_data.estimated_rows,
+            ),
+        };
+        EXECUTION_SHARE + (100.0 - EXECUTION_SHARE) * read
+    }
+}
+
+/// `done` as a share of `total`, at most 1, and 0 when the total isn't known.
+fn fraction(done: u64, total: Option<u64>) -> f64 {
+    total.map_or(0.0, |total| (done as f64 / total.max(1) as f64).min(1.0))
+}
+
+/// Buffers rows across result pages, so every chunk handed to DuckDB holds at
+/// most one vector's worth of rows however large or small the pages are.
+struct RowCursor {
+    rows: VecDeque<Record>,
+    column_types: Vec<ColumnType>,
+    enum_columns: EnumColumns,
+}
+
+impl RowCursor {
+    fn new(enum_columns: EnumColumns) -> Self {
         Self {
-            stream: Box::into_raw(stream),
-            done: false,
-            pagination_index: 0,
+            rows: VecDeque::new(),
+            column_types: Vec::new(),
+            enum_columns,
+        }
+    }
+
+    /// A cursor for columns whose types are already known, rather than taken
+    /// from the metadata of the first page.
+    fn with_column_types(column_types: Vec<ColumnType>) -> Self {
+        Self {
+            rows: VecDeque::new(),
+            column_types,
+            enum_columns: EnumColumns::new(),
         }
     }
+
+    /// Buffer the rows of a `GetQueryResults` page.
+    fn push_page(&mut self, rows: &[Row], metadata: &ResultSetMetadata) {
+        if self.column_types.is_empty() {
+            self.column_types = column_types(metadata, &self.enum_columns);
+        }
+        self.rows.extend(rows.iter().map(|row| {
+            row.data()
+                .unwrap_or_default()
+                .iter()
+                .map(|datum| datum.var_char_value().map(str::to_string))
+                .collect::<Record>()
+        }));
+    }
+
+    fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Write up to `capacity` buffered rows into `chunk`.
+    fn fill_chunk(&mut self, capacity: usize, chunk: &DataChunk) -> Result<()> {
+        let count = capacity.min(self.rows.len());
+        let records: Vec<Record> = self.rows.drain(..count).collect();
+        records_to_duckdb_data_chunk(&records, &self.column_types, chunk)
+    }
 }
 
-/// # Safety
-///
-/// .
-#[no_mangle]
-unsafe extern "C" fn read_athena(info: duckdb_function_info, output: duckdb_data_chunk) {
-    let info = FunctionInfo::from(info);
-    let mut output = DataChunk::from(output);
-
-    let init_data = info.init_data::<ScanInitData>();
-    // if (*init_data).done {
-    //     output.set_len(0);
-    //     return;
-    // }
-
-    let batch = match crate::RUNTIME
-        .block_on(async { futures::StreamExt::next(&mut (*(*init_data).stream).stream).await })
-    {
-        Some(Ok(b)) => Some(b),
-        Some(Err(e)) => {
-            info.set_error(duckdb_athena_rust::Error::DuckDB(e.to_string()));
-            return;
+/// Fill `output` with the next chunk of rows from whichever source the scan reads.
+fn scan(state: &mut ScanState<AthenaScan>, output: &mut DataChunk) -> Result<()> {
+    let init_data = state.init_data;
+    match init_data.source(state.bind_data)? {
+        ResultSource::Paginator(paged) => {
+            let mut paged = paged.lock().expect("result pages lock");
+            read_paged_results(&mut paged, output)
         }
-        None => None,
+        ResultSource::Csv(csv) => {
+            let mut csv = csv.lock().expect("CSV results lock");
+            read_csv_results(&mut csv, output)
+        }
+        ResultSource::Parquet(files) => {
+            let reader = state
+                .local_init_data
+                .as_deref_mut()
+                .ok_or_else(|| anyhow!("Parquet reader was not initialized"))?;
+            read_parquet_results(files, reader, output)
+        }
+        ResultSource::Cached(cached) => {
+            let mut cached = cached.lock().expect("cached results lock");
+            read_cached_results(&mut cached, output)
+        }
+        ResultSource::Plan(plan) => {
+            let capacity = unsafe { duckdb_vector_size() } as usize;
+            plan.lock().expect("plan lock").fill_chunk(capacity, output)
+        }
+    }?;
+    init_data
+        .progress
+        .rows_read
+        .fetch_add(output.len() as u64, Ordering::Relaxed);
+    write_to_cache(init_data, output);
+    Ok(())
+}
+
+/// Copy a chunk of results into the cache, writing the cache entry once all of
+/// them were read. Caching is best effort: failing only leaves this scan uncached.
+fn write_to_cache(init_data: &ScanInitData, output: &DataChunk) {
+    let mut cache_writer = init_data.cache_writer.lock().expect("cache writer lock");
+    let Some(writer) = cache_writer.as_mut() else {
+        return;
     };
+    let result = if !output.is_empty() {
+        writer.append(output)
+    } else if init_data
+        .source
+        .get()
+        .map_or(false, ResultSource::is_exhausted)
+    {
+        cache_writer.take().expect("cache writer").finish()
+    } else {
+        return;
+    };
+    if let Err(e) = result {
+        warn!("Not caching results: {}", e);
+        *cache_writer = None;
+    }
+}
+
+/// Fill `output` with the next vector's worth of rows from the result pages.
+fn read_paged_results(paged: &mut PagedResults, output: &mut DataChunk) -> Result<()> {
+    let capacity = unsafe { duckdb_vector_size() } as usize;
+    while paged.cursor.len() < capacity && !paged.done {
+        let batch = match paged.pages.next_page() {
+            Some(batch) => batch?,
+            None => {
+                paged.done = true;
+                break;
+            }
+        };
 
-    if let Some(b) = batch {
-        let mut rows = b.result_set().unwrap().rows().unwrap();
-        // Athena returns the header in the results 0_o but only in the first page
-        if (*init_data).pagination_index == 0 {
-            rows = &rows[1..];
+        let result_set = batch
+            .result_set()
+            .ok_or_else(|| anyhow!("query results page has no result set"))?;
+        let metadata = result_set
+            .result_set_metadata()
+            .ok_or_else(|| anyhow!("query results page has no column metadata"))?;
+        let mut rows = result_set.rows().unwrap_or_default();
+        if paged.pagination_index == 0 {
+            rows = skip_header(rows, metadata);
+        }
+        paged.cursor.push_page(rows, metadata);
+        paged.pagination_index += 1;
+    }
+
+    paged.cursor.fill_chunk(capacity, output)
+}
+
+/// Fill `output` with the next vector's worth of rows from the S3 CSV results.
+fn read_csv_results(csv: &mut CsvResults, output: &mut DataChunk) -> Result<()> {
+    let capacity = unsafe { duckdb_vector_size() } as usize;
+    let records = crate::RUNTIME.block_on(csv.reader.next_records(capacity))?;
+    csv.done = records.is_empty();
+    records_to_duckdb_data_chunk(&records, &csv.column_types, output)
+}
+
+/// Fill `output` with the next chunk this thread reads from the `UNLOAD`ed Parquet files.
+fn read_parquet_results(
+    files: &ParquetFiles,
+    reader: &mut ParquetReader,
+    output: &mut DataChunk,
+) -> Result<()> {
+    match crate::RUNTIME.block_on(files.next_chunk(reader))? {
+        Some(chunk) => chunk.cast_into(output).map_err(|e| anyhow!(e.to_string())),
+        None => {
+            output.set_len(0);
+            Ok(())
         }
-        let metadata = b.result_set().unwrap().result_set_metadata().unwrap();
-        result_set_to_duckdb_data_chunk(rows, metadata, &mut output)
-            .expect("Couldn't write results");
-    } else {
-        (*init_data).done = true;
-        output.set_len(0);
     }
+}
 
-    (*init_data).pagination_index += 1;
+fn read_cached_results(cached: &mut CachedResults, output: &mut DataChunk) -> Result<()> {
+    match cached.next_chunk() {
+        Some(chunk) => chunk.cast_into(output).map_err(|e| anyhow!(e.to_string())),
+        None => {
+            output.set_len(0);
+            Ok(())
+        }
+    }
 }
 
-pub fn result_set_to_duckdb_data_chunk(
-    rows: &[Row],
-    metadata: &ResultSetMetadata,
+/// Write rows of Athena's string-encoded values into `chunk`, `None` being NULL.
+pub fn records_to_duckdb_data_chunk<S: AsRef<str>>(
+    records: &[Vec<Option<S>>],
+    column_types: &[ColumnType],
     chunk: &DataChunk,
 ) -> Result<()> {
-    // Fill the row
-    // This is asserting the wrong thing (row length vs. column length)
-    // assert!_eq!(rs.rows().unwrap().len(), chunk.num_columns());
-    // let rows = &rs.rows().unwrap()[1..];
-    let result_size = rows.len();
+    let capacity = unsafe { duckdb_vector_size() } as usize;
+    if records.len() > capacity {
+        return Err(anyhow!(
+            "{} rows don't fit in a chunk of {} rows",
+            records.len(),
+            capacity
+        ));
+    }
 
-    for row_idx in 0..result_size {
-        let row = &rows[row_idx];
-        let row_data = row.data().unwrap();
-        for col_idx in 0..row_data.len() {
-            let value = row_data[col_idx].var_char_value().unwrap();
-            let colinfo = &metadata.column_info().unwrap()[col_idx];
-            let ddb_type = map_type(colinfo.r#type().unwrap().to_string()).unwrap();
-            unsafe { populate_column(value, ddb_type, chunk, row_idx, col_idx) };
+    for (row_idx, record) in records.iter().enumerate() {
+        for (col_idx, value) in record.iter().enumerate() {
+            match value {
+                Some(value) => populate_column(
+                    value.as_ref(),
+                    &column_types[col_idx],
+                    chunk,
+                    row_idx,
+                    col_idx,
+                )
+                .map_err(|e| anyhow!(e.to_string()))?,
+                None => chunk.flat_vector(col_idx).set_null(row_idx),
+            }
         }
     }
 
-    chunk.set_len(result_size);
+    chunk.set_len(records.len());
 
     Ok(())
 }
 
+/// `rows` of the first page of results without the header row.
+///
+/// Athena returns the column labels as the first row of the results of `SELECT`
+/// queries, but not of other statements such as DDL or `SHOW`.
+fn skip_header<'a>(rows: &'a [Row], metadata: &ResultSetMetadata) -> &'a [Row] {
+    let labels = metadata
+        .column_info()
+        .unwrap_or_default()
+        .iter()
+        .map(|info| info.label().or_else(|| info.name()));
+    match rows.split_first() {
+        Some((first, rest))
+            if first
+                .data()
+                .unwrap_or_default()
+                .iter()
+                .map(|datum| datum.var_char_value())
+                .eq(labels) =>
+        {
+            rest
+        }
+        _ => rows,
+    }
+}
+
+/// DuckDB types of the columns described by Athena's result set metadata.
+fn column_types(metadata: &ResultSetMetadata, enum_columns: &EnumColumns) -> Vec<ColumnType> {
+    metadata
+        .column_info()
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .map(|(idx, colinfo)| match enum_columns.get(&idx) {
+            Some(dictionary) => ColumnType::Enum(dictionary.clone()),
+            None => match map_type(colinfo.r#type().unwrap_or("varchar").to_string()) {
+                // Nested values, and types DuckDB has no counterpart for, are
+                // declared as the text Athena renders them as
+                Ok(LogicalTypeId::List | LogicalTypeId::Struct) | Err(_) => {
+                    ColumnType::Value(LogicalTypeId::Varchar)
+                }
+                Ok(type_id) => ColumnType::Value(type_id),
+            },
+        })
+        .collect()
+}
+
 fn status(resp: &GetQueryExecutionOutput) -> Option<&QueryExecutionState> {
-    resp.query_execution().unwrap().status().unwrap().state()
+    resp.query_execution()
+        .and_then(|execution| execution.status())
+        .and_then(|status| status.state())
 }
 
 fn total_execution_time(resp: &GetQueryExecutionOutput) -> Option<i64> {
     resp.query_execution()
-        .unwrap()
-        .statistics()
-        .unwrap()
-        .total_execution_time_in_millis()
+        .and_then(|execution| execution.statistics())
+        .and_then(|statistics| statistics.total_execution_time_in_millis())
+}
+
+/// Whether Athena answered the query with the results of an earlier one.
+fn reused_previous_result(resp: &GetQueryExecutionOutput) -> bool {
+    resp.query_execution()
+        .and_then(|execution| execution.statistics())
+        .and_then(|statistics| statistics.result_reuse_information())
+        .map_or(false, |information| information.reused_previous_result())
+}
+
+/// Why Athena says a query failed or was cancelled, with the category of the
+/// error if it has one.
+fn failure_reason(resp: &GetQueryExecutionOutput) -> Option<String> {
+    let status = resp.query_execution()?.status()?;
+    let error = status.athena_error();
+    let reason = status
+        .state_change_reason()
+        .or_else(|| error.and_then(|error| error.error_message()))?;
+    let category = match error.and_then(|error| error.error_category()) {
+        Some(1) => Some("system error"),
+        Some(2) => Some("user error"),
+        Some(3) => Some("unknown error"),
+        _ => None,
+    };
+    Some(match category {
+        Some(category) => format!("{} ({})", reason, category),
+        None => reason.to_string(),
+    })
 }
 
-async fn get_query_result(client: &AthenaClient, query_execution_id: String) -> Result<ResultSet> {
+async fn get_query_result(
+    client: &AthenaClient,
+    retry: &RetryPolicy,
+    query_execution_id: String,
+) -> Result<ResultSet> {
     let resp = anyhow::Context::with_context(
-        client
-            .get_query_results()
-            .set_query_execution_id(Some(query_execution_id.clone()))
-            .send()
+        retry
+            .retry(|| {
+                client
+                    .get_query_results()
+                    .set_query_execution_id(Some(query_execution_id.clone()))
+                    .send()
+            })
             .await,
         || {
             format!(
@@ -218,209 +630,1492 @@ async fn get_query_result(client: &AthenaClient, query_execution_id: String) ->
         .clone())
 }
 
-async fn get_query_result_paginator(
+/// Fetch only the column metadata of a finished query's results.
+async fn get_result_set_metadata(
     client: &AthenaClient,
+    retry: &RetryPolicy,
     query_execution_id: String,
-) -> GetQueryResultsPaginator {
-    client
-        .get_query_results()
-        .set_query_execution_id(Some(query_execution_id.clone()))
-        .into_paginator()
+) -> Result<ResultSetMetadata> {
+    let resp = retry
+        .retry(|| {
+            client
+                .get_query_results()
+                .set_query_execution_id(Some(query_execution_id.clone()))
+                .max_results(1)
+                .send()
+        })
+        .await?;
+
+    resp.result_set()
+        .and_then(|rs| rs.result_set_metadata())
+        .cloned()
+        .ok_or_else(|| anyhow!("could not get query result metadata"))
 }
 
-/// # Safety
-///
-/// .
-#[no_mangle]
-unsafe extern "C" fn read_athena_bind(bind_info: duckdb_bind_info) {
-    let bind_info = BindInfo::from(bind_info);
-    assert!(bind_info.num_parameters()>= 2);
+/// Open the CSV file Athena wrote for a finished query, positioned past its header.
+async fn open_csv_results(
+    s3_client: S3Client,
+    retry: RetryPolicy,
+    output_location: &str,
+    column_types: Vec<ColumnType>,
+) -> Result<CsvResults> {
+    let location = S3Uri::parse(output_location)?;
+    let object = RangedObjectReader::new(s3_client, retry, location, CSV_RANGE_SIZE).await?;
+    let mut reader = CsvResultReader::new(object);
+    reader.next_record().await?;
+
+    Ok(CsvResults {
+        reader,
+        column_types,
+        done: false,
+    })
+}
+
+/// Get the page of results `next_token` points to, the first one without it.
+async fn get_results_page(
+    client: &AthenaClient,
+    retry: &RetryPolicy,
+    query_execution_id: &str,
+    page_size: Option<i32>,
+    next_token: Option<String>,
+) -> ResultPage {
+    retry
+        .retry(|| {
+            client
+                .get_query_results()
+                .query_execution_id(query_execution_id)
+                .set_max_results(page_size)
+                .set_next_token(next_token.clone())
+                .send()
+        })
+        .await
+}
+
+/// The `prefetch_pages` and `page_size` parameters of reading results page by page.
+fn paging_parameters(bind_info: &BindInfo) -> Result<(i32, Option<i32>)> {
+    let prefetch_pages = bind_info
+        .named_parameter("prefetch_pages")
+        .map_or(DEFAULT_PREFETCH_PAGES, |v| v.to_i32());
+    if prefetch_pages < 1 {
+        return Err(anyhow!("prefetch_pages must be at least 1"));
+    }
+    let page_size = bind_info.named_parameter("page_size").map(|v| v.to_i32());
+    if let Some(size) = page_size {
+        if !(1..=MAX_PAGE_SIZE).contains(&size) {
+            return Err(anyhow!("page_size must be between 1 and {}", MAX_PAGE_SIZE));
+        }
+    }
+    Ok((prefetch_pages, page_size))
+}
+
+/// Validate the parameters of a scan and look up its columns in Glue.
+fn bind_scan(bind_info: &BindInfo) -> Result<ScanBindData> {
+    if bind_info.num_parameters() < 2 {
+        return Err(anyhow!(
+            "athena_scan takes a table name and an output location"
+        ));
+    }
 
     let tablename = bind_info.parameter(0);
     let output_location = bind_info.parameter(1);
-    let maxrows = bind_info.named_parameter("maxrows");
-    // let maxrowsd = bind_info.named_parameter("maxrowsd");
-    // println!("Maxrowsd is: {:?}", maxrowsd);
-
-    // How oh how to get named parameters? I don't know!
-    // But postgres does https://github.com/duckdblabs/postgres_scanner/blob/main/postgres_scanner.cpp#LL1059C18-L1059C23
+    let maxrows = bind_info.named_parameter("maxrows").map(|v| v.to_i32());
+    let (prefetch_pages, page_size) = paging_parameters(bind_info)?;
+    let unload_cleanup = bind_info
+        .named_parameter("unload_cleanup")
+        .map_or(true, |v| v.to_bool());
+    let count_rows = bind_info
+        .named_parameter("count_rows")
+        .map_or(false, |v| v.to_bool());
+    let dry_run = bind_info
+        .named_parameter("dry_run")
+        .map_or(false, |v| v.to_bool());
+    let fetch_mode = match bind_info.named_parameter("fetch_mode") {
+        None => FetchMode::Api,
+        Some(value) => FetchMode::parse(&value.to_varchar())?,
+    };
+    let mut enum_names: HashSet<String> = bind_info
+        .named_parameter("enum_columns")
+        .map(|v| v.to_list().iter().map(|v| v.to_varchar()).collect())
+        .unwrap_or_default();
 
     // Table name is the first param that's getting passed in
     // We need to go to the Glue Data Catalog and fetch the column tables for that table.
-    // For now, we only support the `default` table.
-    let config = block_on(aws_config::load_from_env());
-    let client = GlueClient::new(&config);
-
-    let table = client
-        .get_table()
-        .database_name("default")
-        .name(tablename.to_string())
-        .send();
-
-    match crate::RUNTIME.block_on(table) {
-        Ok(resp) => {
-            let columns = resp
-                .table()
-                .unwrap()
-                .storage_descriptor()
-                .unwrap()
-                .columns();
-            for column in columns.unwrap() {
-                let typ = LogicalType::new(
-                    map_type(column.r#type().unwrap_or("varchar").to_string())
-                        .expect("Could not get type"),
+    let table = AthenaTable::parse(&tablename.to_varchar()).map_err(|e| anyhow!(e.to_string()))?;
+    let mut athena_config = AthenaConfig::from_bind(bind_info);
+    if let Some(workgroup) = &table.workgroup {
+        athena_config.workgroup = workgroup.clone();
+    }
+    if let Some(value) = bind_info.named_parameter("reuse_results_max_age") {
+        let minutes = value.to_i32();
+        if !(0..=MAX_REUSE_RESULTS_MAX_AGE).contains(&minutes) {
+            return Err(anyhow!(
+                "reuse_results_max_age must be between 0 and {} minutes",
+                MAX_REUSE_RESULTS_MAX_AGE
+            ));
+        }
+        athena_config.reuse_results_max_age = (minutes > 0).then_some(minutes);
+    }
+    if let Some(value) = bind_info.named_parameter("timeout") {
+        let secs = value.to_i32();
+        if secs < 0 {
+            return Err(anyhow!("timeout must be 0 or more seconds"));
+        }
+        athena_config.query_timeout = (secs > 0).then(|| Duration::from_secs(secs as u64));
+    }
+    let output_location = Some(output_location.to_varchar())
+        .filter(|location| !location.is_empty())
+        .or_else(|| athena_config.output_location.clone())
+        .ok_or_else(|| {
+            anyhow!("no output location, pass one to athena_scan or SET athena_output_location")
+        })?;
+    let limit = maxrows.unwrap_or(athena_config.maxrows);
+
+    let config = block_on(athena_config.aws_config());
+
+    let athena_client = AthenaClient::new(&config);
+    let aws_scope = aws_scope(&config);
+    let mut description = describe_table(&config, &athena_config.retry, &table)?;
+    // Partitioned tables may only have statistics for their partitions
+    let size_needed = athena_config.cost.is_limited() || dry_run;
+    if description.size.is_none() && description.partitioned && size_needed {
+        description.size = partitions_size(&config, &athena_config.retry, &table)?;
+    }
+    // A dry run reports the size instead of refusing the scan
+    if !dry_run {
+        athena_config
+            .cost
+            .check(&table.sql_name(), description.size)?;
+    }
+    let mut enum_columns = EnumColumns::new();
+    let mut result_columns = Vec::new();
+    let mut cache_columns = Vec::new();
+    // A dry run only reads dictionaries from the cache, without them it can't
+    // tell which columns the results would be cached with
+    let mut cache_columns_known = true;
+    for (idx, column) in description.columns.iter().enumerate() {
+        let name = column.name.as_str();
+        let col_type = column.col_type.as_str();
+        let enum_hint = column.enum_hint;
+
+        if enum_names.remove(name) || enum_hint {
+            if map_type(col_type.to_string()).ok() != Some(LogicalTypeId::Varchar) {
+                return Err(anyhow!(
+                    "column {} of type {} can't be an ENUM",
+                    name,
+                    col_type
+                ));
+            }
+            let values = if dry_run {
+                let key = enum_values_key(&athena_config, &table, name, &description, &aws_scope);
+                let values = key.and_then(|key| cached_enum_values(&athena_config, &key));
+                cache_columns_known &= values.is_some();
+                values
+            } else {
+                enum_values(
+                    &athena_client,
+                    &athena_config,
+                    &table,
+                    name,
+                    &output_location,
+                    &description,
+                    &aws_scope,
+                )?
+            };
+            if let Some(values) = values {
+                let values: Vec<&str> = values.iter().map(String::as_str).collect();
+                let typ = LogicalType::enum_type(&values);
+                cache_columns.push(column_definition(name, &typ));
+                result_columns.push((name, typ));
+                enum_columns.insert(idx, Arc::new(enum_dictionary(&values)));
+                continue;
+            }
+            if !dry_run {
+                warn!(
+                    "Column {} has more than {} distinct values, reading it as VARCHAR",
+                    name, MAX_ENUM_VALUES
                 );
-                bind_info.add_result_column(column.name().unwrap(), typ);
             }
         }
-        Err(err) => {
-            bind_info.set_error(duckdb_athena_rust::Error::DuckDB(
-                err.into_service_error().to_string(),
-            ));
-            return;
+
+        let mut typ = map_logical_type(col_type).unwrap_or_else(|_| {
+            warn_unmapped(name, col_type);
+            LogicalType::new(LogicalTypeId::Varchar)
+        });
+        // Only UNLOAD keeps nested values apart, the other modes get them as text
+        if fetch_mode != FetchMode::Unload && !readable_from_text(&typ) {
+            if typ.id() == LogicalTypeId::Map {
+                return Err(anyhow!(
+                    "column {} of type {} can only be read with fetch_mode 'unload'",
+                    name,
+                    col_type
+                ));
+            }
+            typ = LogicalType::new(LogicalTypeId::Varchar);
         }
+        cache_columns.push(column_definition(name, &typ));
+        result_columns.push((name, typ));
+    }
+    if let Some(name) = enum_names.iter().next() {
+        return Err(anyhow!("enum_columns: no column named {}", name));
     }
 
-    unsafe {
-        let bind_data = malloc_struct::<ScanBindData>();
-        (*bind_data).tablename = CString::new(tablename.to_string())
-            .expect("Table name")
-            .into_raw();
-        (*bind_data).output_location = CString::new(output_location.to_string())
-            .expect("S3 output location")
-            .into_raw();
-
-        if !maxrows.is_null() {
-            let lv = maxrows.to_string().parse::<i32>().unwrap();
-            (*bind_data).limit = lv;
-        } else {
-            (*bind_data).limit = DEFAULT_LIMIT;
+    if dry_run {
+        bind_info.add_result_column("plan", LogicalType::new(LogicalTypeId::Varchar));
+        bind_info.add_result_column("line", LogicalType::new(LogicalTypeId::Varchar));
+        if !cache_columns_known {
+            cache_columns.clear();
         }
+        return Ok(ScanBindData {
+            table,
+            output_location,
+            limit,
+            fetch_mode,
+            prefetch_pages,
+            page_size,
+            unload_cleanup,
+            enum_columns: EnumColumns::new(),
+            config: athena_config,
+            update_time: description.update_time,
+            aws_scope,
+            cache_columns,
+            estimated_rows: None,
+            table_size: description.size,
+            dry_run,
+        });
+    }
+    for (name, typ) in result_columns {
+        bind_info.add_result_column(name, typ);
+    }
 
-        bind_info.set_bind_data(bind_data.cast(), Some(drop_scan_bind_data_c));
+    // Glue statistics are an estimate, counting the rows gives the exact number
+    let (mut estimated_rows, exact) = if count_rows {
+        let rows = count_table_rows(
+            &athena_client,
+            &athena_config,
+            &table,
+            &output_location,
+            description.size,
+        )?;
+        (Some(rows), true)
+    } else {
+        (description.record_count, false)
+    };
+    if let Ok(limit) = u64::try_from(limit) {
+        estimated_rows = estimated_rows.map(|rows| rows.min(limit));
+    }
+    if let Some(rows) = estimated_rows {
+        bind_info.set_cardinality(rows as usize, exact);
     }
+    // Counting rows and reading dictionaries may have used up the session's budget
+    athena_config
+        .cost
+        .check_session(&table.sql_name(), description.size)?;
+
+    Ok(ScanBindData {
+        table,
+        output_location,
+        limit,
+        fetch_mode,
+        prefetch_pages,
+        page_size,
+        unload_cleanup,
+        enum_columns,
+        config: athena_config,
+        update_time: description.update_time,
+        aws_scope,
+        cache_columns,
+        estimated_rows,
+        table_size: description.size,
+        dry_run,
+    })
 }
 
-/// # Safety
-///
-/// .
-/// Creates the initial Athena query and waits for it to be done.
-#[no_mangle]
-unsafe extern "C" fn read_athena_init(info: duckdb_init_info) {
-    let info = InitInfo::from(info);
-    let bind_info = info.bind_data::<ScanBindData>();
-    // assert_eq!(bind_info.num_parameters(), 2);
-
-    // Extract the table name and output location from
-    let tablename = CStr::from_ptr((*bind_info).tablename).to_str().unwrap();
-    let output_location = CStr::from_ptr((*bind_info).output_location)
-        .to_str()
-        .unwrap();
-    let maxrows = (*bind_info).limit;
-
-    let config = block_on(aws_config::load_from_env());
-    let client = AthenaClient::new(&config);
-    let result_config = ResultConfiguration::builder()
-        .set_output_location(Some(output_location.to_owned()))
-        .build();
+fn column_definition(name: &str, typ: &LogicalType) -> String {
+    format!("{} {}", quote_identifier(name), duckdb_type_sql(typ))
+}
 
-    let mut query = format!("SELECT * FROM {}", tablename);
-    if maxrows >= 0 {
-        query = format!("{} LIMIT {}", query, maxrows);
-    }
+/// A column of a table as described by its catalog.
+struct TableColumn {
+    name: String,
+    col_type: String,
+    /// Whether the column is marked to be read as `ENUM`
+    enum_hint: bool,
+}
+
+/// What a catalog knows about a table.
+struct TableDescription {
+    columns: Vec<TableColumn>,
+    /// When the table was last updated, in seconds since the epoch
+    update_time: Option<i64>,
+    /// Rows in the table, from its statistics
+    record_count: Option<u64>,
+    /// Size of the table's data in bytes, from its statistics
+    size: Option<u64>,
+    /// Whether the table has partition keys
+    partitioned: bool,
+}
 
-    let athena_query = client
-        .start_query_execution()
-        .set_query_string(Some(query))
-        .set_result_configuration(Some(result_config))
-        .set_work_group(Some("primary".to_string()))
-        .send();
+/// Look up the columns of `table`, in Glue for the account's own catalog and
+/// through Athena for other data catalogs.
+fn describe_table(
+    config: &SdkConfig,
+    retry: &RetryPolicy,
+    table: &AthenaTable,
+) -> Result<TableDescription> {
+    if !table.in_glue_catalog() {
+        let client = AthenaClient::new(config);
+        let metadata = retry.retry(|| {
+            client
+                .get_table_metadata()
+                .set_catalog_name(table.catalog.clone())
+                .database_name(&table.database)
+                .table_name(&table.table)
+                .send()
+        });
+        let resp = crate::RUNTIME
+            .block_on(metadata)
+            .map_err(|err| anyhow!(err.into_service_error().to_string()))?;
+        let columns = resp
+            .table_metadata()
+            .and_then(|metadata| metadata.columns())
+            .unwrap_or_default();
+        let columns = columns
+            .iter()
+            .map(|column| {
+                Ok(TableColumn {
+                    name: column
+                        .name()
+                        .ok_or_else(|| anyhow!("a column of {} has no name", table.sql_name()))?
+                        .to_string(),
+                    col_type: column.r#type().unwrap_or("varchar").to_string(),
+                    enum_hint: false,
+                })
+            })
+            .collect::<Result<_>>()?;
+        return Ok(TableDescription {
+            columns,
+            update_time: None,
+            record_count: None,
+            size: None,
+            partitioned: false,
+        });
+    }
 
-    // TODO: Use unwrap_or maybe? Docs recommend not to use this because it can panic.
+    let client = GlueClient::new(config);
+    let get_table = retry.retry(|| {
+        client
+            .get_table()
+            .database_name(&table.database)
+            .name(&table.table)
+            .send()
+    });
     let resp = crate::RUNTIME
-        .block_on(athena_query)
-        .expect("could not start query");
+        .block_on(get_table)
+        .map_err(|err| anyhow!(err.into_service_error().to_string()))?;
+    let glue_table = resp
+        .table()
+        .ok_or_else(|| anyhow!("Glue returned no table for {}", table.sql_name()))?;
+    let columns = glue_table
+        .storage_descriptor()
+        .and_then(|descriptor| descriptor.columns())
+        .ok_or_else(|| anyhow!("Glue returned no columns for {}", table.sql_name()))?;
+    let columns = columns
+        .iter()
+        .map(|column| {
+            Ok(TableColumn {
+                name: column
+                    .name()
+                    .ok_or_else(|| anyhow!("a column of {} has no name", table.sql_name()))?
+                    .to_string(),
+                col_type: column.r#type().unwrap_or("varchar").to_string(),
+                // Columns can be marked as ENUM in Glue as well
+                enum_hint: column
+                    .parameters()
+                    .and_then(|params| params.get(ENUM_COLUMN_PARAMETER))
+                    .map_or(false, |value| value.eq_ignore_ascii_case("true")),
+            })
+        })
+        .collect::<Result<_>>()?;
+    // Statistics Glue crawlers keep in the table parameters
+    let statistic = |name: &str| {
+        glue_table
+            .parameters()
+            .and_then(|params| params.get(name))
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    Ok(TableDescription {
+        columns,
+        update_time: glue_table.update_time().map(|time| time.secs()),
+        record_count: statistic("recordCount"),
+        size: statistic("sizeKey"),
+        partitioned: glue_table
+            .partition_keys()
+            .map_or(false, |keys| !keys.is_empty()),
+    })
+}
+
+/// Size of a partitioned table in bytes, the sum of the statistics of its
+/// partitions, if they all have them.
+fn partitions_size(
+    config: &SdkConfig,
+    retry: &RetryPolicy,
+    table: &AthenaTable,
+) -> Result<Option<u64>> {
+    let client = GlueClient::new(config);
+    crate::RUNTIME.block_on(async {
+        let mut size = 0;
+        let mut next_token = None;
+        loop {
+            let page = retry
+                .retry(|| {
+                    client
+                        .get_partitions()
+                        .database_name(&table.database)
+                        .table_name(&table.table)
+                        .set_next_token(next_token.clone())
+                        .send()
+                })
+                .await
+                .map_err(|err| anyhow!(err.into_service_error().to_string()))?;
+            for partition in page.partitions().unwrap_or_default() {
+                let partition_size = partition
+                    .parameters()
+                    .and_then(|params| params.get("sizeKey"))
+                    .and_then(|value| value.parse::<u64>().ok());
+                match partition_size {
+                    Some(partition_size) => size += partition_size,
+                    None => return Ok(None),
+                }
+            }
+            next_token = page.next_token().map(str::to_string);
+            if next_token.is_none() {
+                return Ok(Some(size));
+            }
+        }
+    })
+}
+
+/// Start `query` in the configured workgroup, returning its execution id.
+fn start_query(
+    client: &AthenaClient,
+    config: &AthenaConfig,
+    query: String,
+    output_location: &str,
+) -> Result<String> {
+    let result_config = ResultConfiguration::builder()
+        .set_output_location(Some(output_location.to_owned()))
+        .build();
+    let reuse_config = config.reuse_results_max_age.map(|minutes| {
+        ResultReuseConfiguration::builder()
+            .result_reuse_by_age_configuration(
+                ResultReuseByAgeConfiguration::builder()
+                    .enabled(true)
+                    .max_age_in_minutes(minutes)
+                    .build(),
+            )
+            .build()
+    });
+    let request_token = request_token();
+    let athena_query = config.retry.retry(|| {
+        client
+            .start_query_execution()
+            .set_query_string(Some(query.clone()))
+            .set_result_configuration(Some(result_config.clone()))
+            .set_work_group(Some(config.workgroup.clone()))
+            .set_result_reuse_configuration(reuse_config.clone())
+            .client_request_token(&request_token)
+            .send()
+    });
+
+    let resp = crate::RUNTIME.block_on(athena_query)?;
 
     let query_execution_id = resp.query_execution_id().unwrap_or_default();
-    println!(
-        "Running Athena query, execution id: {}",
-        &query_execution_id
-    );
+    info!("Running Athena query, execution id: {}", query_execution_id);
+    Ok(query_execution_id.to_string())
+}
 
-    let mut state: QueryExecutionState;
+/// Wait for a query the extension started, counting what it scanned towards
+/// the session's cost. The bytes scanned so far are recorded in `progress`
+/// at each poll.
+fn wait_for_query(
+    client: &AthenaClient,
+    config: &AthenaConfig,
+    query_execution_id: &str,
+    progress: Option<&ScanProgress>,
+) -> Result<GetQueryExecutionOutput> {
+    let mut scanned = 0;
+    let resp = poll_query(client, config, query_execution_id, |bytes| {
+        scanned = bytes;
+        if let Some(progress) = progress {
+            progress.bytes_scanned.store(bytes, Ordering::Relaxed);
+        }
+    });
+    // Cancelled queries are billed for what they scanned too
+    cost::record(scanned);
+    resp
+}
 
+/// Poll a query until it is done, failing if it was cancelled or failed. Polls
+/// are quick at first, for short queries, and back off to `poll_interval`.
+/// A query running longer than `query_timeout` is cancelled. `on_poll` is
+/// passed the bytes scanned so far at each poll.
+fn poll_query(
+    client: &AthenaClient,
+    config: &AthenaConfig,
+    query_execution_id: &str,
+    mut on_poll: impl FnMut(u64),
+) -> Result<GetQueryExecutionOutput> {
+    let started = Instant::now();
+    let mut interval = INITIAL_POLL_INTERVAL;
     loop {
-        let get_query = client
-            .get_query_execution()
-            .set_query_execution_id(Some(query_execution_id.to_string()))
-            .send();
+        let get_query = config.retry.retry(|| {
+            client
+                .get_query_execution()
+                .set_query_execution_id(Some(query_execution_id.to_string()))
+                .send()
+        });
 
-        let resp = crate::RUNTIME
-            .block_on(get_query)
-            .expect("Could not get query status");
-        state = status(&resp).expect("could not get query status").clone();
+        let resp = crate::RUNTIME.block_on(get_query)?;
+        let state = status(&resp)
+            .ok_or_else(|| anyhow!("could not get query status"))?
+            .clone();
+        let scanned = resp
+            .query_execution()
+            .and_then(|execution| execution.statistics())
+            .and_then(|statistics| statistics.data_scanned_in_bytes());
+        if let Some(scanned) = scanned {
+            on_poll(scanned.max(0) as u64);
+        }
 
         match state {
             Queued | Running => {
-                thread::sleep(Duration::from_secs(5));
-                println!("State: {:?}, sleep 5 secs ...", state);
+                let mut sleep = interval;
+                if let Some(timeout) = config.query_timeout {
+                    let remaining = timeout.saturating_sub(started.elapsed());
+                    if remaining.is_zero() {
+                        crate::RUNTIME.block_on(config.retry.retry(|| {
+                            client
+                                .stop_query_execution()
+                                .query_execution_id(query_execution_id)
+                                .send()
+                        }))?;
+                        return Err(anyhow!(
+                            "Athena query {} did not finish within {} seconds and was cancelled",
+                            query_execution_id,
+                            timeout.as_secs()
+                        ));
+                    }
+                    sleep = sleep.min(remaining);
+                }
+                debug!("State: {:?}, sleep {} millis ...", state, sleep.as_millis());
+                thread::sleep(sleep);
+                interval = (interval * 2).min(config.poll_interval);
             }
             Cancelled | Failed => {
-                println!("State: {:?}", state);
-
-                match crate::RUNTIME
-                    .block_on(get_query_result(&client, query_execution_id.to_string()))
-                {
-                    Ok(result) => println!("Result: {:?}", result),
-                    Err(e) => println!("Result error: {:?}", e),
-                }
-
-                break;
+                error!("State: {:?}", state);
+                let message = format!(
+                    "Athena query {} ended in state {:?}",
+                    query_execution_id, state
+                );
+                return Err(match failure_reason(&resp) {
+                    Some(reason) => anyhow!("{}: {}", message, reason),
+                    None => anyhow!(message),
+                });
             }
             _ => {
-                let millis = total_execution_time(&resp).unwrap();
-                println!("Total execution time: {} millis", millis);
-
-                // let stream = match crate::RUNTIME.block_on(async {
-                //     let paginator = get_query_result_paginator(&client, query_execution_id.to_string()).await;
-                //     let results = paginator.send();
-                //     results
-                // }) {
-                //     Ok(s) => Box::new(s),
-                // };
-                let paginator = crate::RUNTIME.block_on(async {
-                    get_query_result_paginator(&client, query_execution_id.to_string()).await
-                });
-                let stream = paginator.send();
+                let millis = total_execution_time(&resp).unwrap_or_default();
+                info!("Total execution time: {} millis", millis);
+                if config.reuse_results_max_age.is_some() {
+                    info!("Reused previous results: {}", reused_previous_result(&resp));
+                }
+                return Ok(resp);
+            }
+        }
+    }
+}
 
-                let init_data = Box::new(ScanInitData::new(Box::new(ResultStream::new(Box::new(
-                    stream,
-                )))));
-                info.set_init_data(Box::into_raw(init_data).cast(), Some(duckdb_free));
-                break;
+/// The distinct values of a string column, sorted, to serve as its `ENUM` dictionary.
+/// `None` if there are more than [MAX_ENUM_VALUES] of them.
+fn distinct_values(
+    client: &AthenaClient,
+    config: &AthenaConfig,
+    query: String,
+    output_location: &str,
+) -> Result<Option<Vec<String>>> {
+    let mut values = run_for_first_column(client, config, query, output_location)?;
+    if values.len() > MAX_ENUM_VALUES {
+        return Ok(None);
+    }
+
+    values.sort();
+    values.dedup();
+    Ok(Some(values))
+}
+
+/// The AWS region and profile queries run under. The same names may refer to
+/// other tables in other regions and accounts, so both are part of cache keys.
+fn aws_scope(sdk_config: &SdkConfig) -> [String; 2] {
+    [
+        sdk_config
+            .region()
+            .map(|region| region.as_ref().to_string())
+            .unwrap_or_default(),
+        std::env::var("AWS_PROFILE").unwrap_or_default(),
+    ]
+}
+
+/// The query reading the `ENUM` dictionary of a column.
+fn enum_values_query(table: &AthenaTable, column: &str) -> String {
+    format!(
+        "SELECT DISTINCT {} FROM {} LIMIT {}",
+        quote_identifier(column),
+        table.sql_name(),
+        MAX_ENUM_VALUES + 1
+    )
+}
+
+/// The key the `ENUM` dictionary of a column is cached under, `None` when the
+/// cache is disabled.
+fn enum_values_key(
+    config: &AthenaConfig,
+    table: &AthenaTable,
+    column: &str,
+    description: &TableDescription,
+    aws_scope: &[String; 2],
+) -> Option<String> {
+    config.cache.enabled.then(|| {
+        let update_time = description.update_time.map(|t| t.to_string());
+        cache_key(
+            &enum_values_query(table, column),
+            &[
+                table.catalog.as_deref().unwrap_or_default(),
+                &table.database,
+                &config.workgroup,
+                update_time.as_deref().unwrap_or_default(),
+                &aws_scope[0],
+                &aws_scope[1],
+            ],
+        )
+    })
+}
+
+/// The `ENUM` dictionary cached under `key`, if there is one.
+fn cached_enum_values(config: &AthenaConfig, key: &str) -> Option<Vec<String>> {
+    let database = config.database().ok()?;
+    config.cache.lookup_values(database, key)
+}
+
+/// The `ENUM` dictionary of a string column, `None` if it has too many distinct
+/// values. Dictionaries are kept in the results cache when it is enabled, so
+/// that scans reading cached results don't query Athena for them again.
+fn enum_values(
+    client: &AthenaClient,
+    config: &AthenaConfig,
+    table: &AthenaTable,
+    column: &str,
+    output_location: &str,
+    description: &TableDescription,
+    aws_scope: &[String; 2],
+) -> Result<Option<Vec<String>>> {
+    let key = enum_values_key(config, table, column, description, aws_scope);
+    let cached = key
+        .as_deref()
+        .and_then(|key| cached_enum_values(config, key));
+    if let Some(values) = cached {
+        debug!("Read the values of column {} from the cache", column);
+        return Ok(Some(values));
+    }
+
+    // Reading a column scans at most the whole table
+    config
+        .cost
+        .check_session(&table.sql_name(), description.size)?;
+    let query = enum_values_query(table, column);
+    let values = distinct_values(client, config, query, output_location)?;
+    if let (Some(key), Some(values)) = (&key, &values) {
+        let stored = config
+            .database()
+            .map_err(|e| anyhow!(e.to_string()))
+            .and_then(|database| config.cache.store_values(database, key, values));
+        if let Err(e) = stored {
+            warn!("Not caching the values of column {}: {}", column, e);
+        }
+    }
+    Ok(values)
+}
+
+/// Run `query`, returning the values of the first column of its results,
+/// without the header and the nulls.
+fn run_for_first_column(
+    client: &AthenaClient,
+    config: &AthenaConfig,
+    query: String,
+    output_location: &str,
+) -> Result<Vec<String>> {
+    let query_execution_id = start_query(client, config, query, output_location)?;
+    wait_for_query(client, config, &query_execution_id, None)?;
+
+    crate::RUNTIME.block_on(async {
+        let mut values = Vec::new();
+        let mut next_token = None;
+        let mut first_page = true;
+        loop {
+            let page = get_results_page(
+                client,
+                &config.retry,
+                &query_execution_id,
+                None,
+                next_token.take(),
+            )
+            .await?;
+            let result_set = page.result_set();
+            let mut rows = result_set.and_then(|rs| rs.rows()).unwrap_or_default();
+            if let Some(metadata) = result_set.and_then(|rs| rs.result_set_metadata()) {
+                if first_page {
+                    rows = skip_header(rows, metadata);
+                }
+            }
+            first_page = false;
+            for row in rows {
+                let datum = row.data().and_then(|data| data.first());
+                if let Some(value) = datum.and_then(|datum| datum.var_char_value()) {
+                    values.push(value.to_string());
+                }
+            }
+            next_token = page.next_token().map(str::to_string);
+            if next_token.is_none() {
+                return Ok(values);
             }
         }
+    })
+}
+
+/// The number of rows in `table`, counted by Athena.
+fn count_table_rows(
+    client: &AthenaClient,
+    config: &AthenaConfig,
+    table: &AthenaTable,
+    output_location: &str,
+    table_size: Option<u64>,
+) -> Result<u64> {
+    config.cost.check_session(&table.sql_name(), table_size)?;
+    let query = format!("SELECT COUNT(*) FROM {}", table.sql_name());
+    let query_execution_id = start_query(client, config, query, output_location)?;
+    wait_for_query(client, config, &query_execution_id, None)?;
+
+    let result =
+        crate::RUNTIME.block_on(get_query_result(client, &config.retry, query_execution_id))?;
+    let rows = result.rows().unwrap_or_default();
+    let rows = match result.result_set_metadata() {
+        Some(metadata) => skip_header(rows, metadata),
+        None => rows,
+    };
+    rows.first()
+        .and_then(|row| row.data())
+        .and_then(|data| data.first())
+        .and_then(|datum| datum.var_char_value())
+        .and_then(|count| count.parse().ok())
+        .ok_or_else(|| anyhow!("could not count the rows of {}", table.sql_name()))
+}
+
+/// The query a scan runs, before it is wrapped in `UNLOAD`.
+fn scan_query(bind_data: &ScanBindData) -> String {
+    let query = format!("SELECT * FROM {}", bind_data.table.sql_name());
+    match bind_data.limit {
+        limit if limit >= 0 => format!("{} LIMIT {}", query, limit),
+        _ => query,
+    }
+}
+
+/// The key the results of a scan running `query` are cached under, `None` when
+/// the cache is disabled.
+fn scan_cache_key(bind_data: &ScanBindData, query: &str) -> Option<String> {
+    bind_data.config.cache.enabled.then(|| {
+        let update_time = bind_data.update_time.map(|t| t.to_string());
+        cache_key(
+            query,
+            &[
+                bind_data.table.catalog.as_deref().unwrap_or_default(),
+                &bind_data.table.database,
+                &bind_data.config.workgroup,
+                update_time.as_deref().unwrap_or_default(),
+                &bind_data.cache_columns.join(", "),
+                &bind_data.aws_scope[0],
+                &bind_data.aws_scope[1],
+            ],
+        )
+    })
+}
+
+/// Plans Athena explains queries with, and what the dry run calls them.
+const EXPLAIN_TYPES: [(&str, &str); 2] = [("distributed", "DISTRIBUTED"), ("io", "IO")];
+
+/// The SQL a scan would send to Athena, an estimate of what it would scan and
+/// Athena's distributed and IO plans for it, one line per row. Explaining a
+/// query doesn't scan any data.
+fn dry_run(bind_data: &ScanBindData) -> Result<ResultSource> {
+    let query = scan_query(bind_data);
+    let sent = match bind_data.fetch_mode {
+        FetchMode::Unload => UnloadPrefix::new(&bind_data.output_location)?.unload_query(&query),
+        _ => query.clone(),
+    };
+    // Without the columns it isn't known which cached results the scan would read
+    let cached = scan_cache_key(bind_data, &query)
+        .filter(|_| !bind_data.cache_columns.is_empty())
+        .and_then(|key| bind_data.config.cache.lookup(&key));
+    let estimate = scan_estimate(
+        &bind_data.table.sql_name(),
+        bind_data.table_size,
+        cached.as_deref(),
+        &bind_data.config.cost,
+    );
+    let mut sections = vec![("sql", vec![sent]), ("estimate", estimate)];
+
+    let config = block_on(bind_data.config.aws_config());
+    let client = AthenaClient::new(&config);
+    for (plan, explain_type) in EXPLAIN_TYPES {
+        let explain = format!("EXPLAIN (TYPE {}) {}", explain_type, query);
+        let lines = run_for_first_column(
+            &client,
+            &bind_data.config,
+            explain,
+            &bind_data.output_location,
+        )?;
+        sections.push((plan, lines));
+    }
+    Ok(ResultSource::Plan(Mutex::new(plan_rows(&sections))))
+}
+
+/// What a scan of `table` would cost: nothing when its results are cached in
+/// `cached`, otherwise the bytes Athena would scan at most and their price.
+fn scan_estimate(
+    table: &str,
+    table_size: Option<u64>,
+    cached: Option<&Path>,
+    cost: &CostConfig,
+) -> Vec<String> {
+    if let Some(path) = cached {
+        return vec![format!(
+            "results are cached in {}, Athena won't be queried",
+            path.display()
+        )];
+    }
+    let Some(bytes) = table_size else {
+        return vec![format!(
+            "size of {} is unknown, so are the bytes it would scan",
+            table
+        )];
+    };
+    let mut lines = vec![format!(
+        "scans up to {} bytes of {}, about ${:.2}",
+        bytes,
+        table,
+        cost.estimated_cost(bytes)
+    )];
+    if let Err(e) = cost.check(table, Some(bytes)) {
+        lines.push(format!("would be refused: {}", e));
+    }
+    lines
+}
+
+/// Rows of `(plan, line)` for the sections of a dry run, with one row for each
+/// line of their text.
+fn plan_rows(sections: &[(&str, Vec<String>)]) -> RowCursor {
+    let mut cursor = RowCursor::with_column_types(vec![
+        ColumnType::Value(LogicalTypeId::Varchar),
+        ColumnType::Value(LogicalTypeId::Varchar),
+    ]);
+    for (plan, lines) in sections {
+        cursor.rows.extend(
+            lines
+                .iter()
+                .flat_map(|line| line.lines())
+                .map(|line| vec![Some(plan.to_string()), Some(line.to_string())]),
+        );
+    }
+    cursor
+}
+
+/// Starts the Athena query of a scan, unless its results are cached. The query
+/// is waited for by the first call to [ScanInitData::source].
+fn init_scan(info: &InitInfo, bind_data: &ScanBindData) -> Result<ScanInitData> {
+    let output_location = &bind_data.output_location;
+    if bind_data.dry_run {
+        return Ok(ScanInitData {
+            source: OnceLock::from(dry_run(bind_data)?),
+            pending: Mutex::new(None),
+            progress: ScanProgress::default(),
+            cache_writer: Mutex::new(None),
+        });
+    }
+
+    let mut query = scan_query(bind_data);
+    let cache = &bind_data.config.cache;
+    let key = scan_cache_key(bind_data, &query);
+    if let Some(path) = key.as_deref().and_then(|key| cache.lookup(key)) {
+        info!("Reading cached results from {}", path.display());
+        let database = bind_data
+            .config
+            .database()
+            .map_err(|e| anyhow!(e.to_string()))?;
+        let cached = CachedResults::open(database, &path)?;
+        return Ok(ScanInitData {
+            source: OnceLock::from(ResultSource::Cached(Mutex::new(cached))),
+            pending: Mutex::new(None),
+            progress: ScanProgress::default(),
+            cache_writer: Mutex::new(None),
+        });
     }
+
+    let sdk_config = block_on(bind_data.config.aws_config());
+    let client = AthenaClient::new(&sdk_config);
+
+    let mut unload_prefix = None;
+    if bind_data.fetch_mode == FetchMode::Unload {
+        let prefix = UnloadPrefix::new(output_location)?;
+        query = prefix.unload_query(&query);
+        unload_prefix = Some(prefix);
+        // Every file can be read by its own thread. How many files there are
+        // is only known once the query finished, threads left without one
+        // are done straight away.
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        info.set_max_threads(threads);
+    }
+
+    let query_execution_id = start_query(&client, &bind_data.config, query, output_location)?;
+
+    let columns = &bind_data.cache_columns;
+    let cache_writer = match key.map(|key| {
+        let database = bind_data
+            .config
+            .database()
+            .map_err(|e| anyhow!(e.to_string()))?;
+        CacheWriter::new(database, cache, &key, columns)
+    }) {
+        Some(Ok(writer)) => Some(writer),
+        Some(Err(e)) => {
+            warn!("Not caching results: {}", e);
+            None
+        }
+        None => None,
+    };
+    Ok(ScanInitData {
+        source: OnceLock::new(),
+        pending: Mutex::new(Some(PendingQuery {
+            client,
+            sdk_config,
+            query_execution_id,
+            unload_prefix,
+        })),
+        progress: ScanProgress::default(),
+        cache_writer: Mutex::new(cache_writer),
+    })
 }
 
-pub fn build_table_function_def() -> TableFunction {
-    let table_function = TableFunction::new("athena_scan");
-    let logical_type = LogicalType::new(LogicalTypeId::Varchar);
-    let int_type = LogicalType::new(LogicalTypeId::Integer);
-    table_function.add_parameter(&logical_type);
-    table_function.add_parameter(&logical_type);
-    // table_function.add_parameter(&int_type);
-    // For some reason, we can't use limit here...
-    table_function.add_named_parameter("maxrows", &int_type);
+/// Wait for the query of a scan to be done, and open its results.
+fn open_results(
+    bind_data: &ScanBindData,
+    query: PendingQuery,
+    progress: &ScanProgress,
+) -> Result<ResultSource> {
+    let PendingQuery {
+        client,
+        sdk_config,
+        query_execution_id,
+        unload_prefix,
+    } = query;
+    let resp = wait_for_query(
+        &client,
+        &bind_data.config,
+        &query_execution_id,
+        Some(progress),
+    )?;
+
+    let source = match bind_data.fetch_mode {
+        FetchMode::Api => {
+            let pages = PagePrefetcher::spawn(
+                client,
+                bind_data.config.retry.clone(),
+                query_execution_id,
+                bind_data.page_size,
+                bind_data.prefetch_pages as usize,
+            );
+            let cursor = RowCursor::new(bind_data.enum_columns.clone());
+            ResultSource::Paginator(Mutex::new(PagedResults::new(pages, cursor)))
+        }
+        FetchMode::Csv => {
+            let output_location = resp
+                .query_execution()
+                .and_then(|qe| qe.result_configuration())
+                .and_then(|rc| rc.output_location())
+                .unwrap_or_default();
+            let csv = crate::RUNTIME.block_on(async {
+                let metadata =
+                    get_result_set_metadata(&client, &bind_data.config.retry, query_execution_id)
+                        .await?;
+                let column_types = column_types(&metadata, &bind_data.enum_columns);
+                open_csv_results(
+                    S3Client::new(&sdk_config),
+                    bind_data.config.retry.clone(),
+                    output_location,
+                    column_types,
+                )
+                .await
+            });
+            ResultSource::Csv(Mutex::new(csv?))
+        }
+        FetchMode::Unload => {
+            let prefix = unload_prefix.expect("UNLOAD prefix");
+            let parquet = crate::RUNTIME.block_on(ParquetFiles::open(
+                S3Client::new(&sdk_config),
+                bind_data.config.retry.clone(),
+                prefix,
+                bind_data.unload_cleanup,
+            ))?;
+            ResultSource::Parquet(parquet)
+        }
+    };
+    Ok(source)
+}
 
-    table_function.set_function(Some(read_athena));
-    table_function.set_init(Some(read_athena_init));
-    table_function.set_bind(Some(read_athena_bind));
-    table_function
+pub fn duckdb_error(e: anyhow::Error) -> duckdb_athena_rust::Error {
+    duckdb_athena_rust::Error::DuckDB(e.to_string())
 }
 
-lazy_static::lazy_static! {
-    static ref RUNTIME: Runtime = tokio::runtime::Builder::new_current_thread()
+/// `athena_scan(table, output_location)`: read an Athena table into DuckDB.
+struct AthenaScan;
+
+impl VTab for AthenaScan {
+    type BindData = ScanBindData;
+    type InitData = ScanInitData;
+    type LocalInitData = ParquetReader;
+
+    fn bind(bind: &BindInfo) -> duckdb_athena_rust::Result<ScanBindData> {
+        bind_scan(bind).map_err(duckdb_error)
+    }
+
+    fn init(init: &InitInfo, bind_data: &ScanBindData) -> duckdb_athena_rust::Result<ScanInitData> {
+        init_scan(init, bind_data).map_err(duckdb_error)
+    }
+
+    /// Only `UNLOAD` results are read by more than one thread, each with its own
+    /// connection for reading Parquet files.
+    fn local_init(
+        _init: &InitInfo,
+        bind_data: &ScanBindData,
+    ) -> duckdb_athena_rust::Result<Option<ParquetReader>> {
+        if bind_data.fetch_mode != FetchMode::Unload {
+            return Ok(None);
+        }
+        let connection = bind_data.config.database()?.connect()?;
+        Ok(Some(ParquetReader::new(connection)))
+    }
+
+    fn func(state: &mut ScanState<Self>, output: &mut DataChunk) -> duckdb_athena_rust::Result<()> {
+        scan(state, output).map_err(duckdb_error)
+    }
+
+    fn progress(bind_data: &ScanBindData, init_data: &ScanInitData) -> Option<f64> {
+        Some(init_data.progress(bind_data))
+    }
+
+    fn parameters() -> Vec<LogicalType> {
+        vec![
+            LogicalType::new(LogicalTypeId::Varchar),
+            LogicalType::new(LogicalTypeId::Varchar),
+        ]
+    }
+
+    fn named_parameters() -> Vec<(&'static str, LogicalType)> {
+        // For some reason, we can't use limit here...
+        vec![
+            ("maxrows", LogicalType::new(LogicalTypeId::Integer)),
+            ("fetch_mode", LogicalType::new(LogicalTypeId::Varchar)),
+            ("prefetch_pages", LogicalType::new(LogicalTypeId::Integer)),
+            ("page_size", LogicalType::new(LogicalTypeId::Integer)),
+            ("unload_cleanup", LogicalType::new(LogicalTypeId::Boolean)),
+            (
+                "reuse_results_max_age",
+                LogicalType::new(LogicalTypeId::Integer),
+            ),
+            ("timeout", LogicalType::new(LogicalTypeId::Integer)),
+            ("count_rows", LogicalType::new(LogicalTypeId::Boolean)),
+            ("dry_run", LogicalType::new(LogicalTypeId::Boolean)),
+            (
+                "enum_columns",
+                LogicalType::list_type(&LogicalType::new(LogicalTypeId::Varchar)),
+            ),
+        ]
+    }
+}
+
+/// Bind data of `athena_results`.
+struct ResultsBindData {
+    query_execution_id: String,
+    column_types: Vec<ColumnType>,
+    prefetch_pages: i32,
+    page_size: Option<i32>,
+    config: AthenaConfig,
+}
+
+/// Note that a column of a type DuckDB has no counterpart for, such as `json`
+/// or `time`, is read as the text Athena renders its values as.
+fn warn_unmapped(name: &str, col_type: &str) {
+    warn!(
+        "Column {} has type {}, which DuckDB has no counterpart for, reading it as VARCHAR",
+        name, col_type
+    );
+}
+
+/// The DuckDB type of a result column, and how its values are written. Result
+/// metadata has no key and value types for maps, so those are read as strings.
+/// Types DuckDB has no counterpart for are read as `VARCHAR` too.
+fn result_column_type(info: &ColumnInfo) -> (LogicalType, ColumnType) {
+    let col_type = info.r#type().unwrap_or("varchar");
+    let type_id = map_type(col_type.to_string()).unwrap_or_else(|_| {
+        warn_unmapped(info.name().unwrap_or_default(), col_type);
+        LogicalTypeId::Varchar
+    });
+    match type_id {
+        LogicalTypeId::Decimal => (
+            LogicalType::decimal(info.precision() as u8, info.scale() as u8),
+            ColumnType::Value(LogicalTypeId::Decimal),
+        ),
+        LogicalTypeId::Map | LogicalTypeId::List | LogicalTypeId::Struct => (
+            LogicalType::new(LogicalTypeId::Varchar),
+            ColumnType::Value(LogicalTypeId::Varchar),
+        ),
+        type_id => (LogicalType::new(type_id), ColumnType::Value(type_id)),
+    }
+}
+
+/// Wait for an existing query to finish and declare the columns of its results.
+fn bind_results(bind_info: &BindInfo) -> Result<ResultsBindData> {
+    let query_execution_id = bind_info.parameter(0).to_varchar();
+    let (prefetch_pages, page_size) = paging_parameters(bind_info)?;
+    let mut athena_config = AthenaConfig::from_bind(bind_info);
+    // The query may not be ours to cancel
+    athena_config.query_timeout = None;
+    let config = block_on(athena_config.aws_config());
+    let client = AthenaClient::new(&config);
+
+    // Usually done already, but a query may still be running after a crash
+    poll_query(&client, &athena_config, &query_execution_id, |_| {})?;
+    let metadata = crate::RUNTIME.block_on(get_result_set_metadata(
+        &client,
+        &athena_config.retry,
+        query_execution_id.clone(),
+    ))?;
+
+    let mut column_types = Vec::new();
+    for info in metadata.column_info().unwrap_or_default() {
+        let (typ, column_type) = result_column_type(info);
+        bind_info.add_result_column(info.name().unwrap_or_default(), typ);
+        column_types.push(column_type);
+    }
+
+    Ok(ResultsBindData {
+        query_execution_id,
+        column_types,
+        prefetch_pages,
+        page_size,
+        config: athena_config,
+    })
+}
+
+fn init_results(bind_data: &ResultsBindData) -> Result<Mutex<PagedResults>> {
+    let config = block_on(bind_data.config.aws_config());
+    let pages = PagePrefetcher::spawn(
+        AthenaClient::new(&config),
+        bind_data.config.retry.clone(),
+        bind_data.query_execution_id.clone(),
+        bind_data.page_size,
+        bind_data.prefetch_pages as usize,
+    );
+    let cursor = RowCursor::with_column_types(bind_data.column_types.clone());
+    Ok(Mutex::new(PagedResults::new(pages, cursor)))
+}
+
+/// `athena_results(query_execution_id)`: read the results of a query that was
+/// already run, in the console, by another job or before DuckDB went down.
+struct AthenaResults;
+
+impl VTab for AthenaResults {
+    type BindData = ResultsBindData;
+    type InitData = Mutex<PagedResults>;
+    type LocalInitData = ();
+
+    fn bind(bind: &BindInfo) -> duckdb_athena_rust::Result<ResultsBindData> {
+        bind_results(bind).map_err(duckdb_error)
+    }
+
+    fn init(
+        _init: &InitInfo,
+        bind_data: &ResultsBindData,
+    ) -> duckdb_athena_rust::Result<Mutex<PagedResults>> {
+        init_results(bind_data).map_err(duckdb_error)
+    }
+
+    fn func(state: &mut ScanState<Self>, output: &mut DataChunk) -> duckdb_athena_rust::Result<()> {
+        let mut paged = state.init_data.lock().expect("result pages lock");
+        read_paged_results(&mut paged, output).map_err(duckdb_error)
+    }
+
+    fn parameters() -> Vec<LogicalType> {
+        vec![LogicalType::new(LogicalTypeId::Varchar)]
+    }
+
+    fn named_parameters() -> Vec<(&'static str, LogicalType)> {
+        vec![
+            ("prefetch_pages", LogicalType::new(LogicalTypeId::Integer)),
+            ("page_size", LogicalType::new(LogicalTypeId::Integer)),
+        ]
+    }
+}
+
+pub fn register_table_function(connection: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+    connection.register_vtab::<AthenaScan>("athena_scan")?;
+    connection.register_vtab::<AthenaResults>("athena_results")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_athena::model::{AthenaError, Datum, QueryExecution, QueryExecutionStatus};
+
+    fn row(values: &[&str]) -> Row {
+        values
+            .iter()
+            .fold(Row::builder(), |row, value| {
+                row.data(Datum::builder().var_char_value(*value).build())
+            })
+            .build()
+    }
+
+    fn metadata(labels: &[&str]) -> ResultSetMetadata {
+        labels
+            .iter()
+            .fold(ResultSetMetadata::builder(), |metadata, label| {
+                metadata.column_info(ColumnInfo::builder().name(*label).label(*label).build())
+            })
+            .build()
+    }
+
+    fn bigint_cursor() -> RowCursor {
+        RowCursor::with_column_types(vec![ColumnType::Value(LogicalTypeId::Bigint)])
+    }
+
+    fn drain(cursor: &mut RowCursor, capacity: usize) -> Vec<Vec<i64>> {
+        let mut chunks = Vec::new();
+        loop {
+            let chunk = DataChunk::new(&[LogicalType::new(LogicalTypeId::Bigint)]);
+            cursor.fill_chunk(capacity, &chunk).unwrap();
+            if chunk.is_empty() {
+                return chunks;
+            }
+            chunks.push(
+                (0..chunk.len())
+                    .map(|row| chunk.value(0, row).to_i64())
+                    .collect(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_row_cursor_splits_pages() {
+        let capacity = unsafe { duckdb_vector_size() } as usize;
+        let metadata = metadata(&["n"]);
+        let page: Vec<Row> = (0..capacity + 10).map(|n| row(&[&n.to_string()])).collect();
+
+        let mut cursor = bigint_cursor();
+        cursor.push_page(&page, &metadata);
+        let chunks = drain(&mut cursor, capacity);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], (0..capacity as i64).collect::<Vec<_>>());
+        assert_eq!(
+            chunks[1],
+            (capacity as i64..capacity as i64 + 10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_row_cursor_joins_pages() {
+        let capacity = unsafe { duckdb_vector_size() } as usize;
+        let metadata = metadata(&["n"]);
+        let half = capacity / 2 + 1;
+        let first: Vec<Row> = (0..half).map(|n| row(&[&n.to_string()])).collect();
+        let second: Vec<Row> = (half..2 * half).map(|n| row(&[&n.to_string()])).collect();
+
+        let mut cursor = bigint_cursor();
+        cursor.push_page(&first, &metadata);
+        cursor.push_page(&second, &metadata);
+        let chunks = drain(&mut cursor, capacity);
+        let expected: Vec<i64> = (0..2 * half as i64).collect();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], expected[..capacity]);
+        assert_eq!(chunks[1], expected[capacity..]);
+    }
+
+    #[test]
+    fn test_records_exceeding_chunk() {
+        let capacity = unsafe { duckdb_vector_size() } as usize;
+        let records = vec![vec![Some("1")]; capacity + 1];
+        let chunk = DataChunk::new(&[LogicalType::new(LogicalTypeId::Bigint)]);
+        let column_types = [ColumnType::Value(LogicalTypeId::Bigint)];
+        assert!(records_to_duckdb_data_chunk(&records, &column_types, &chunk).is_err());
+    }
+
+    fn failed_query(reason: Option<&str>, category: Option<i32>) -> GetQueryExecutionOutput {
+        let error = category.map(|category| {
+            AthenaError::builder()
+                .error_category(category)
+                .error_message("error message")
+                .build()
+        });
+        let status = QueryExecutionStatus::builder()
+            .state(Failed)
+            .set_state_change_reason(reason.map(str::to_string))
+            .set_athena_error(error)
+            .build();
+        GetQueryExecutionOutput::builder()
+            .query_execution(QueryExecution::builder().status(status).build())
             .build()
-            .expect("runtime");
+    }
+
+    #[test]
+    fn test_failure_reason() {
+        assert_eq!(
+            failure_reason(&failed_query(Some("TABLE_NOT_FOUND: line 1:15"), Some(2))).as_deref(),
+            Some("TABLE_NOT_FOUND: line 1:15 (user error)")
+        );
+        assert_eq!(
+            failure_reason(&failed_query(Some("Query exhausted resources"), None)).as_deref(),
+            Some("Query exhausted resources")
+        );
+        assert_eq!(
+            failure_reason(&failed_query(None, Some(1))).as_deref(),
+            Some("error message (system error)")
+        );
+        assert_eq!(failure_reason(&failed_query(None, None)), None);
+    }
+
+    #[test]
+    fn test_result_column_type() {
+        let column = |col_type: &str| {
+            let info = ColumnInfo::builder()
+                .name("c")
+                .r#type(col_type)
+                .precision(10)
+                .scale(2)
+                .build();
+            match result_column_type(&info) {
+                (typ, ColumnType::Value(type_id)) => (typ.id(), type_id),
+                (_, ColumnType::Enum(_)) => panic!("{} read as an ENUM", col_type),
+            }
+        };
+        let bigint = LogicalTypeId::Bigint;
+        let decimal = LogicalTypeId::Decimal;
+        let varchar = LogicalTypeId::Varchar;
+        assert_eq!(column("bigint"), (bigint, bigint));
+        assert_eq!(column("decimal"), (decimal, decimal));
+        assert_eq!(column("array"), (varchar, varchar));
+        assert_eq!(column("json"), (varchar, varchar));
+        assert_eq!(column("time"), (varchar, varchar));
+    }
+
+    #[test]
+    fn test_skip_header() {
+        let metadata = metadata(&["id", "name"]);
+
+        let rows = [row(&["id", "name"]), row(&["1", "a"])];
+        assert_eq!(skip_header(&rows, &metadata), &rows[1..]);
+
+        // DDL and SHOW results have no header row
+        let rows = [row(&["1", "a"]), row(&["2", "b"])];
+        assert_eq!(skip_header(&rows, &metadata), &rows[..]);
+
+        assert!(skip_header(&[], &metadata).is_empty());
+    }
+
+    fn unlimited() -> CostConfig {
+        CostConfig {
+            max_bytes_scanned: None,
+            session_max_bytes_scanned: None,
+            price_per_tb: 5.0,
+        }
+    }
+
+    #[test]
+    fn test_scan_estimate() {
+        let tb = 1024 * 1024 * 1024 * 1024;
+        assert_eq!(
+            scan_estimate("db.t", Some(tb), None, &unlimited()),
+            ["scans up to 1099511627776 bytes of db.t, about $5.00"]
+        );
+
+        let limited = CostConfig {
+            max_bytes_scanned: Some(tb / 2),
+            ..unlimited()
+        };
+        let estimate = scan_estimate("db.t", Some(tb), None, &limited);
+        assert_eq!(estimate.len(), 2);
+        assert!(estimate[1].starts_with("would be refused: scanning db.t"));
+        assert_eq!(scan_estimate("db.t", Some(tb / 2), None, &limited).len(), 1);
+    }
+
+    #[test]
+    fn test_scan_estimate_cached() {
+        let path = Path::new("/cache/results.parquet");
+        // Cached results cost nothing, whatever the table's size
+        for size in [Some(1 << 40), None] {
+            assert_eq!(
+                scan_estimate("db.t", size, Some(path), &unlimited()),
+                ["results are cached in /cache/results.parquet, Athena won't be queried"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_scan_estimate_unknown_size() {
+        let limited = CostConfig {
+            max_bytes_scanned: Some(1),
+            ..unlimited()
+        };
+        assert_eq!(
+            scan_estimate("db.t", None, None, &limited),
+            ["size of db.t is unknown, so are the bytes it would scan"]
+        );
+    }
+
+    #[test]
+    fn test_plan_rows() {
+        let sections = [
+            (
+                "sql",
+                vec!["SELECT * FROM \"db\".\"t\" LIMIT 10".to_string()],
+            ),
+            ("estimate", vec!["size of db.t is unknown".to_string()]),
+            (
+                "distributed",
+                vec![
+                    "Fragment 0\n    Output".to_string(),
+                    "Fragment 1".to_string(),
+                ],
+            ),
+            ("io", vec![]),
+        ];
+        let rows: Vec<Record> = plan_rows(&sections).rows.into_iter().collect();
+        let expected = [
+            ("sql", "SELECT * FROM \"db\".\"t\" LIMIT 10"),
+            ("estimate", "size of db.t is unknown"),
+            ("distributed", "Fragment 0"),
+            ("distributed", "    Output"),
+            ("distributed", "Fragment 1"),
+        ];
+        let expected: Vec<Record> = expected
+            .iter()
+            .map(|(plan, line)| vec![Some(plan.to_string()), Some(line.to_string())])
+            .collect();
+        assert_eq!(rows, expected);
+    }
 }
@@ -1,14 +1,23 @@
-use std::{ffi::CString, slice};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
 
-use duckdb_athena_rust::duckdb_vector_size;
-use duckdb_athena_rust::{DataChunk, Inserter, LogicalTypeId};
+use duckdb_athena_rust::{
+    DataChunk, Date, Decimal, EnumIndex, FlatVector, LogicalType, LogicalTypeId, MapVector,
+    Timestamp,
+};
 
 use crate::error::{Error, Result};
 
+/// Default precision and scale of an Athena `decimal` declared without them.
+const DEFAULT_DECIMAL: (u8, u8) = (10, 0);
+
 // Maps Athena data types to DuckDB types
 // Supported types are listed here: https://docs.aws.amazon.com/athena/latest/ug/data-types.html
 pub fn map_type(col_type: String) -> Result<LogicalTypeId> {
-    let type_id = match col_type.as_str() {
+    // Parameters such as the length of `varchar(10)` don't change the type
+    let base_type = col_type.split(['(', '<']).next().unwrap_or_default().trim();
+    let type_id = match base_type {
         "boolean" => LogicalTypeId::Boolean,
         "tinyint" => LogicalTypeId::Tinyint,
         "smallint" => LogicalTypeId::Smallint,
@@ -18,6 +27,10 @@ pub fn map_type(col_type: String) -> Result<LogicalTypeId> {
         "float" => LogicalTypeId::Float,
         "decimal" => LogicalTypeId::Decimal,
         "string" | "varchar" | "char" => LogicalTypeId::Varchar,
+        "binary" | "varbinary" => LogicalTypeId::Blob,
+        "map" => LogicalTypeId::Map,
+        "array" => LogicalTypeId::List,
+        "struct" | "row" => LogicalTypeId::Struct,
         "date" => LogicalTypeId::Date,
         "timestamp" => LogicalTypeId::Timestamp,
         _ => {
@@ -28,42 +41,599 @@ pub fn map_type(col_type: String) -> Result<LogicalTypeId> {
     Ok(type_id)
 }
 
-pub unsafe fn populate_column(
+/// Maps an Athena data type to the full DuckDB type, keeping the precision and
+/// scale of `decimal(p, s)`, the key and value types of `map<k, v>` and the
+/// element and field types of `array<t>`, `struct<a:t>` and `row(a t)`.
+pub fn map_logical_type(col_type: &str) -> Result<LogicalType> {
+    match map_type(col_type.to_string())? {
+        LogicalTypeId::Decimal => {
+            let (width, scale) = decimal_parameters(col_type)?;
+            Ok(LogicalType::decimal(width, scale))
+        }
+        LogicalTypeId::Map => {
+            let (key_type, value_type) = map_parameters(col_type)?;
+            Ok(LogicalType::map_type(
+                &map_logical_type(key_type)?,
+                &map_logical_type(value_type)?,
+            ))
+        }
+        LogicalTypeId::List => {
+            let element_type = type_parameters(col_type)?;
+            Ok(LogicalType::list_type(&map_logical_type(element_type)?))
+        }
+        LogicalTypeId::Struct => {
+            let fields = struct_fields(col_type)?
+                .into_iter()
+                .map(|(name, field_type)| Ok((name, map_logical_type(field_type)?)))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(LogicalType::struct_type(&fields))
+        }
+        type_id => Ok(LogicalType::new(type_id)),
+    }
+}
+
+/// Whether values of `logical_type` can be read from the text Athena renders
+/// them as, which nested lists and structs can't.
+pub fn readable_from_text(logical_type: &LogicalType) -> bool {
+    match logical_type.id() {
+        LogicalTypeId::List | LogicalTypeId::Struct => false,
+        LogicalTypeId::Map => {
+            readable_from_text(&logical_type.map_key_type())
+                && readable_from_text(&logical_type.map_value_type())
+        }
+        _ => true,
+    }
+}
+
+/// What is between the outer brackets of a type such as `array<t>` or `row(a t)`.
+fn type_parameters(col_type: &str) -> Result<&str> {
+    let col_type = col_type.trim();
+    col_type
+        .find(['<', '('])
+        .and_then(|start| {
+            let close = match col_type.as_bytes()[start] {
+                b'<' => '>',
+                _ => ')',
+            };
+            col_type[start + 1..].strip_suffix(close)
+        })
+        .map(str::trim)
+        .ok_or_else(|| Error::DuckDB(format!("Unsupported data type: {col_type}")))
+}
+
+/// Split `params` at the commas that aren't nested in brackets.
+fn split_parameters(params: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (idx, c) in params.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(params[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(params[start..].trim());
+    parts
+}
+
+/// Split `map<k, v>` into `k` and `v`, either of which may be nested types.
+fn map_parameters(col_type: &str) -> Result<(&str, &str)> {
+    let invalid = || Error::DuckDB(format!("Unsupported data type: {col_type}"));
+    let params = col_type
+        .trim()
+        .strip_prefix("map<")
+        .and_then(|params| params.strip_suffix('>'))
+        .ok_or_else(invalid)?;
+
+    match split_parameters(params)[..] {
+        [key_type, value_type] => Ok((key_type, value_type)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Names and types of the fields of `struct<a:t, b:u>` or `row(a t, b u)`.
+fn struct_fields(col_type: &str) -> Result<Vec<(&str, &str)>> {
+    let invalid = || Error::DuckDB(format!("Unsupported data type: {col_type}"));
+    split_parameters(type_parameters(col_type)?)
+        .into_iter()
+        .map(|field| {
+            let (name, field_type) = field
+                .split_once(|c: char| c == ':' || c.is_whitespace())
+                .ok_or_else(invalid)?;
+            let name = name.trim_matches(|c| c == '"' || c == '`');
+            if name.is_empty() {
+                return Err(invalid());
+            }
+            Ok((name, field_type.trim()))
+        })
+        .collect()
+}
+
+fn decimal_parameters(col_type: &str) -> Result<(u8, u8)> {
+    let params = match col_type.split_once('(') {
+        Some((_, params)) => params.trim_end_matches(')'),
+        None => return Ok(DEFAULT_DECIMAL),
+    };
+    let invalid = || Error::DuckDB(format!("Unsupported data type: {col_type}"));
+    let mut params = params.split(',').map(|p| p.trim().parse::<u8>());
+    let width = params.next().ok_or_else(invalid)?.map_err(|_| invalid())?;
+    let scale = params.next().unwrap_or(Ok(0)).map_err(|_| invalid())?;
+    if !(1..=38).contains(&width) || scale > width {
+        return Err(invalid());
+    }
+    Ok((width, scale))
+}
+
+/// The name DuckDB uses for a type in SQL.
+pub fn duckdb_type_name(type_id: LogicalTypeId) -> &'static str {
+    match type_id {
+        LogicalTypeId::Boolean => "BOOLEAN",
+        LogicalTypeId::Tinyint => "TINYINT",
+        LogicalTypeId::Smallint => "SMALLINT",
+        LogicalTypeId::Integer => "INTEGER",
+        LogicalTypeId::Bigint => "BIGINT",
+        LogicalTypeId::UTinyint => "UTINYINT",
+        LogicalTypeId::USmallint => "USMALLINT",
+        LogicalTypeId::UInteger => "UINTEGER",
+        LogicalTypeId::UBigint => "UBIGINT",
+        LogicalTypeId::Float => "FLOAT",
+        LogicalTypeId::Double => "DOUBLE",
+        LogicalTypeId::Timestamp => "TIMESTAMP",
+        LogicalTypeId::Date => "DATE",
+        LogicalTypeId::Time => "TIME",
+        LogicalTypeId::Interval => "INTERVAL",
+        LogicalTypeId::Hugeint => "HUGEINT",
+        LogicalTypeId::Varchar => "VARCHAR",
+        LogicalTypeId::Blob => "BLOB",
+        LogicalTypeId::Decimal => "DECIMAL",
+        LogicalTypeId::TimestampS => "TIMESTAMP_S",
+        LogicalTypeId::TimestampMs => "TIMESTAMP_MS",
+        LogicalTypeId::TimestampNs => "TIMESTAMP_NS",
+        LogicalTypeId::Enum => "ENUM",
+        LogicalTypeId::List => "LIST",
+        LogicalTypeId::Struct => "STRUCT",
+        LogicalTypeId::Map => "MAP",
+        LogicalTypeId::Uuid => "UUID",
+        LogicalTypeId::Union => "UNION",
+    }
+}
+
+/// How DuckDB SQL spells a full type, e.g. `DECIMAL(10,2)` or `MAP(VARCHAR, BIGINT)`.
+pub fn duckdb_type_sql(logical_type: &LogicalType) -> String {
+    match logical_type.id() {
+        LogicalTypeId::Decimal => format!(
+            "DECIMAL({},{})",
+            logical_type.decimal_width(),
+            logical_type.decimal_scale()
+        ),
+        LogicalTypeId::Map => format!(
+            "MAP({}, {})",
+            duckdb_type_sql(&logical_type.map_key_type()),
+            duckdb_type_sql(&logical_type.map_value_type())
+        ),
+        LogicalTypeId::List => format!("{}[]", duckdb_type_sql(&logical_type.child(0))),
+        LogicalTypeId::Struct => {
+            let fields: Vec<String> = (0..logical_type.num_children())
+                .map(|idx| {
+                    format!(
+                        "{} {}",
+                        quote_identifier(&logical_type.child_name(idx)),
+                        duckdb_type_sql(&logical_type.child(idx))
+                    )
+                })
+                .collect();
+            format!("STRUCT({})", fields.join(", "))
+        }
+        LogicalTypeId::Enum => {
+            let values: Vec<String> = logical_type
+                .enum_values()
+                .iter()
+                .map(|value| format!("'{}'", value.replace('\'', "''")))
+                .collect();
+            format!("ENUM({})", values.join(", "))
+        }
+        type_id => duckdb_type_name(type_id).to_string(),
+    }
+}
+
+/// Quote a column or table name for SQL.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Dictionary indices of the values of an `ENUM` column.
+pub type EnumDictionary = HashMap<String, u32>;
+
+pub fn enum_dictionary(values: &[&str]) -> EnumDictionary {
+    (0..)
+        .zip(values)
+        .map(|(idx, value)| (value.to_string(), idx))
+        .collect()
+}
+
+/// How the values of a result column are written into DuckDB vectors.
+#[derive(Debug, Clone)]
+pub enum ColumnType {
+    Value(LogicalTypeId),
+    /// A string column read as `ENUM`, written as indices into its dictionary.
+    Enum(Arc<EnumDictionary>),
+}
+
+/// Write one of Athena's string-encoded values into the output chunk.
+pub fn populate_column(
     value: &str,
-    col_type: LogicalTypeId,
+    col_type: &ColumnType,
     output: &DataChunk,
     row_idx: usize,
     col_idx: usize,
-) {
+) -> Result<()> {
+    match col_type {
+        ColumnType::Enum(dictionary) => {
+            let vector = output.flat_vector(col_idx);
+            match dictionary.get(value) {
+                Some(index) => vector.set(row_idx, EnumIndex(*index))?,
+                // A value that appeared after the dictionary was looked up
+                None => vector.set_null(row_idx),
+            }
+            Ok(())
+        }
+        ColumnType::Value(LogicalTypeId::Map) => {
+            let map_type = output.flat_vector(col_idx).logical_type();
+            populate_map(value, &map_type, &mut output.map_vector(col_idx), row_idx)
+        }
+        ColumnType::Value(type_id) => {
+            populate_value(value, *type_id, &output.flat_vector(col_idx), row_idx)
+        }
+    }
+}
+
+fn populate_value(
+    value: &str,
+    col_type: LogicalTypeId,
+    vector: &FlatVector,
+    row_idx: usize,
+) -> Result<()> {
     match col_type {
-        LogicalTypeId::Varchar => set_bytes(output, row_idx, col_idx, value.as_bytes()),
-        LogicalTypeId::Bigint => {
-            let cvalue = value.parse::<i64>();
-            assign(output, row_idx, col_idx, cvalue)
+        LogicalTypeId::Varchar => vector.set(row_idx, value)?,
+        LogicalTypeId::Blob => {
+            let bytes = parse_hex(value).ok_or_else(|| invalid_value(value, col_type))?;
+            vector.set(row_idx, bytes.as_slice())?
+        }
+        LogicalTypeId::Boolean => vector.set(row_idx, parse::<bool>(value, col_type)?)?,
+        LogicalTypeId::Tinyint => vector.set(row_idx, parse::<i8>(value, col_type)?)?,
+        LogicalTypeId::Smallint => vector.set(row_idx, parse::<i16>(value, col_type)?)?,
+        LogicalTypeId::Integer => vector.set(row_idx, parse::<i32>(value, col_type)?)?,
+        LogicalTypeId::Bigint => vector.set(row_idx, parse::<i64>(value, col_type)?)?,
+        LogicalTypeId::Float => vector.set(row_idx, parse::<f32>(value, col_type)?)?,
+        LogicalTypeId::Double => vector.set(row_idx, parse::<f64>(value, col_type)?)?,
+        LogicalTypeId::Decimal => vector.set(row_idx, parse::<Decimal>(value, col_type)?)?,
+        LogicalTypeId::Date => {
+            let days = parse_date(value).ok_or_else(|| invalid_value(value, col_type))?;
+            vector.set(row_idx, Date(days))?
         }
-        LogicalTypeId::Integer => {
-            let cvalue = value.parse::<i32>();
-            assign(output, row_idx, col_idx, cvalue)
+        LogicalTypeId::Timestamp => {
+            let micros = parse_timestamp(value).ok_or_else(|| invalid_value(value, col_type))?;
+            vector.set(row_idx, Timestamp(micros))?
         }
         _ => {
-            println!("Unsupported data type: {:?}", col_type);
+            return Err(Error::DuckDB(format!(
+                "Unsupported data type: {:?}",
+                col_type
+            )))
         }
     }
+    Ok(())
 }
 
-unsafe fn assign<T: 'static>(output: &DataChunk, row_idx: usize, col_idx: usize, v: T) {
-    get_column_result_vector::<T>(output, col_idx)[row_idx] = v;
+/// Days since the Unix epoch of a `YYYY-MM-DD` date.
+fn parse_date(value: &str) -> Option<i32> {
+    let mut parts = value.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    // Days from civil, counting in eras of 400 years that start on March 1st
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    i32::try_from(era * 146_097 + day_of_era - 719_468).ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Microseconds since the Unix epoch of a `YYYY-MM-DD HH:MM:SS[.fraction]`
+/// timestamp, digits of the fraction past microseconds being dropped.
+fn parse_timestamp(value: &str) -> Option<i64> {
+    let (date, time) = value.split_once([' ', 'T'])?;
+    let days = i64::from(parse_date(date)?);
+
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, fraction),
+        None => (time, ""),
+    };
+    let mut parts = time.splitn(3, ':');
+    let hours: u32 = parts.next()?.parse().ok()?;
+    let minutes: u32 = parts.next()?.parse().ok()?;
+    let seconds: u32 = parts.next()?.parse().ok()?;
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let micros = format!("{:0<6}", &fraction[..fraction.len().min(6)])
+        .parse::<i64>()
+        .ok()?;
+
+    let seconds =
+        ((days * 24 + i64::from(hours)) * 60 + i64::from(minutes)) * 60 + i64::from(seconds);
+    Some(seconds * 1_000_000 + micros)
+}
+
+/// Append the entries of a map, which Athena renders as `{key1=value1, key2=value2}`,
+/// and point row `row_idx` at them.
+fn populate_map(
+    value: &str,
+    map_type: &LogicalType,
+    vector: &mut MapVector,
+    row_idx: usize,
+) -> Result<()> {
+    let key_type = map_type.map_key_type().id();
+    let value_type = map_type.map_value_type();
+    let string_values = value_type.id() == LogicalTypeId::Varchar;
+    let entries =
+        parse_map(value, string_values).ok_or_else(|| invalid_value(value, LogicalTypeId::Map))?;
+
+    let offset = vector.len();
+    let len = offset + entries.len();
+    let keys = vector.keys(len);
+    let values = vector.values(len);
+    let mut nested_maps =
+        (value_type.id() == LogicalTypeId::Map).then(|| MapVector::from(vector.values(len)));
+    for (idx, (key, value)) in entries.into_iter().enumerate() {
+        populate_value(key, key_type, &keys, offset + idx)?;
+        // NULL and the string "null" look the same, strings are kept as they are
+        if value == "null" && !string_values {
+            values.set_null(offset + idx);
+        } else if let Some(nested_maps) = &mut nested_maps {
+            populate_map(value, &value_type, nested_maps, offset + idx)?;
+        } else {
+            populate_value(value, value_type.id(), &values, offset + idx)?;
+        }
+    }
+    vector.set_entry(row_idx, offset, len - offset);
+    vector.set_len(len);
+    Ok(())
+}
+
+/// Split a map Athena rendered as `{key1=value1, key2=value2}` into its entries.
+///
+/// Nothing is quoted, so separators are only looked for outside the brackets
+/// and braces of nested values. A `, ` that isn't followed by a `key=` is part
+/// of the previous value. Keys end at their first `=` if values are strings,
+/// which may hold `=` themselves, and at their last one otherwise.
+fn parse_map(value: &str, string_values: bool) -> Option<Vec<(&str, &str)>> {
+    let entries = value.strip_prefix('{')?.strip_suffix('}')?;
+    if entries.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let mut separators: Vec<usize> = top_level_chars(entries)
+        .filter(|&(idx, c)| c == ',' && entries[idx + 1..].starts_with(' '))
+        .map(|(idx, _)| idx)
+        .collect();
+    separators.push(entries.len());
+
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    for end in separators {
+        let has_key = top_level_chars(&entries[start..end]).any(|(_, c)| c == '=');
+        match spans.last_mut() {
+            Some(last) if !has_key => last.1 = end,
+            _ => spans.push((start, end)),
+        }
+        start = end + 2;
+    }
+
+    spans
+        .into_iter()
+        .map(|(start, end)| {
+            let entry = &entries[start..end];
+            let mut equals = top_level_chars(entry).filter(|&(_, c)| c == '=');
+            let (idx, _) = if string_values {
+                equals.next()?
+            } else {
+                equals.last()?
+            };
+            Some((&entry[..idx], &entry[idx + 1..]))
+        })
+        .collect()
 }
 
-unsafe fn get_column_result_vector<T>(output: &DataChunk, column_index: usize) -> &'static mut [T] {
-    let result_vector = output.flat_vector(column_index);
-    // result_vector.as_mut_slice::<T>() or similar _should_ work here
-    let ptr = result_vector.as_mut_ptr::<T>();
-    slice::from_raw_parts_mut(ptr, duckdb_vector_size() as usize)
+/// The characters of a rendered value that aren't nested in brackets or
+/// braces, with their positions.
+fn top_level_chars(value: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut depth = 0;
+    value.char_indices().filter(move |&(_, c)| match c {
+        '{' | '[' | '(' => {
+            depth += 1;
+            false
+        }
+        '}' | ']' | ')' => {
+            depth -= 1;
+            false
+        }
+        _ => depth == 0,
+    })
 }
 
-unsafe fn set_bytes(output: &DataChunk, row_idx: usize, col_idx: usize, bytes: &[u8]) {
-    let cs = CString::new(bytes).unwrap();
-    let result_vector = &mut output.flat_vector(col_idx);
-    result_vector.insert(row_idx, cs.to_str().unwrap());
+fn parse<T: FromStr>(value: &str, col_type: LogicalTypeId) -> Result<T> {
+    value.parse().map_err(|_| invalid_value(value, col_type))
+}
+
+fn invalid_value(value: &str, col_type: LogicalTypeId) -> Error {
+    Error::DuckDB(format!("Invalid {:?} value: {}", col_type, value))
+}
+
+/// Decode `varbinary` values, which Athena renders as space-separated hex bytes.
+fn parse_hex(value: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = value.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_map() {
+        assert_eq!(
+            parse_map("{a=1, b=2}", false),
+            Some(vec![("a", "1"), ("b", "2")])
+        );
+        assert_eq!(parse_map("{}", false), Some(vec![]));
+        assert_eq!(parse_map("a=1", false), None);
+        assert_eq!(parse_map("{a}", false), None);
+    }
+
+    #[test]
+    fn test_parse_map_nested() {
+        assert_eq!(
+            parse_map("{a={x=1, y=2}, b={}}", false),
+            Some(vec![("a", "{x=1, y=2}"), ("b", "{}")])
+        );
+        assert_eq!(
+            parse_map("{a=[1, 2], b=[3]}", false),
+            Some(vec![("a", "[1, 2]"), ("b", "[3]")])
+        );
+    }
+
+    #[test]
+    fn test_parse_map_separators_in_values() {
+        assert_eq!(
+            parse_map("{a=x, y, b=z}", true),
+            Some(vec![("a", "x, y"), ("b", "z")])
+        );
+        assert_eq!(
+            parse_map("{a=x=y, b=null}", true),
+            Some(vec![("a", "x=y"), ("b", "null")])
+        );
+        // Values that can't hold `=` leave the key with all but the last one
+        assert_eq!(
+            parse_map("{a=b=1, c=null}", false),
+            Some(vec![("a=b", "1"), ("c", "null")])
+        );
+    }
+
+    #[test]
+    fn test_map_parameters() {
+        assert_eq!(
+            map_parameters("map<string,int>").unwrap(),
+            ("string", "int")
+        );
+        assert_eq!(
+            map_parameters("map<string, map<int, array<string>>>").unwrap(),
+            ("string", "map<int, array<string>>")
+        );
+        assert_eq!(
+            map_parameters("map<string,decimal(10,2)>").unwrap(),
+            ("string", "decimal(10,2)")
+        );
+        assert!(map_parameters("map<string>").is_err());
+        assert!(map_parameters("map<a,b,c>").is_err());
+        assert!(map_parameters("array<int>").is_err());
+    }
+
+    #[test]
+    fn test_decimal_parameters() {
+        assert_eq!(decimal_parameters("decimal(10,2)").unwrap(), (10, 2));
+        assert_eq!(decimal_parameters("decimal(38, 38)").unwrap(), (38, 38));
+        assert_eq!(decimal_parameters("decimal(5)").unwrap(), (5, 0));
+        assert_eq!(decimal_parameters("decimal").unwrap(), DEFAULT_DECIMAL);
+        assert!(decimal_parameters("decimal(39,2)").is_err());
+        assert!(decimal_parameters("decimal(5,6)").is_err());
+        assert!(decimal_parameters("decimal(0)").is_err());
+        assert!(decimal_parameters("decimal(x)").is_err());
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("de ad be ef"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(parse_hex("00FF"), Some(vec![0x00, 0xff]));
+        assert_eq!(parse_hex(""), Some(vec![]));
+        assert_eq!(parse_hex("abc"), None);
+        assert_eq!(parse_hex("zz"), None);
+    }
+
+    #[test]
+    fn test_struct_fields() {
+        assert_eq!(
+            struct_fields("struct<a:int,b:map<string,array<int>>>").unwrap(),
+            vec![("a", "int"), ("b", "map<string,array<int>>")]
+        );
+        assert_eq!(
+            struct_fields("row(a integer, b row(c varchar, d decimal(10,2)))").unwrap(),
+            vec![("a", "integer"), ("b", "row(c varchar, d decimal(10,2))")]
+        );
+        assert!(struct_fields("struct<int>").is_err());
+        assert!(struct_fields("struct").is_err());
+    }
+
+    #[test]
+    fn test_type_parameters() {
+        assert_eq!(type_parameters("array<array<int>>").unwrap(), "array<int>");
+        assert_eq!(type_parameters("row(a int)").unwrap(), "a int");
+        assert!(type_parameters("array<int").is_err());
+        assert!(type_parameters("array").is_err());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2000-03-01"), Some(11_017));
+        assert_eq!(parse_date("1969-12-31"), Some(-1));
+        assert_eq!(parse_date("2024-02-29"), Some(19_782));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2023-13-01"), None);
+        assert_eq!(parse_date("2023-01"), None);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-01 00:00:00"), Some(0));
+        assert_eq!(
+            parse_timestamp("2000-03-01 12:34:56.789"),
+            Some(((11_017 * 24 + 12) * 3600 + 34 * 60 + 56) * 1_000_000 + 789_000)
+        );
+        assert_eq!(
+            parse_timestamp("1970-01-01T00:00:01.123456789"),
+            Some(1_123_456)
+        );
+        assert_eq!(parse_timestamp("1970-01-01 24:00:00"), None);
+        assert_eq!(parse_timestamp("1970-01-01"), None);
+        assert_eq!(parse_timestamp("1970-01-01 00:00:00.5x"), None);
+    }
 }
@@ -0,0 +1,223 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use aws_sdk_s3::Client as S3Client;
+use duckdb_athena_rust::{Connection, DataChunk, QueryResult};
+
+use crate::logging::warn;
+use crate::retry::RetryPolicy;
+use crate::s3::{delete_objects, download_object, list_objects, S3Uri};
+
+static UNLOAD_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A fresh S3 prefix below the query output location for `UNLOAD` to write to.
+///
+/// Athena refuses to `UNLOAD` into a prefix that already holds objects, so
+/// every scan gets its own.
+pub struct UnloadPrefix {
+    id: String,
+    location: S3Uri,
+}
+
+impl UnloadPrefix {
+    pub fn new(output_location: &str) -> Result<Self> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+        let id = format!(
+            "{:x}-{:x}-{}",
+            nanos,
+            std::process::id(),
+            UNLOAD_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let base = S3Uri::parse(output_location)?;
+        let mut key = base.key.trim_matches('/').to_string();
+        if !key.is_empty() {
+            key.push('/');
+        }
+        key.push_str(&format!("duckdb-athena-unload/{}/", id));
+
+        Ok(Self {
+            id,
+            location: S3Uri {
+                bucket: base.bucket,
+                key,
+            },
+        })
+    }
+
+    /// Wrap `query` so that Athena writes its results as Parquet below this prefix.
+    pub fn unload_query(&self, query: &str) -> String {
+        format!(
+            "UNLOAD ({}) TO '{}' WITH (format = 'PARQUET')",
+            query, self.location
+        )
+    }
+}
+
+/// The Parquet files written by an `UNLOAD`, shared by all threads scanning them.
+///
+/// Each file is downloaded by whichever thread picks it up and scanned with
+/// DuckDB's own Parquet reader, so column types come through as Athena wrote them.
+///
+/// The files are cleaned up after the last one has been read, or when the scan
+/// is dropped before that, such as for a `LIMIT`.
+pub struct ParquetFiles {
+    s3_client: S3Client,
+    retry: RetryPolicy,
+    location: S3Uri,
+    files: Vec<S3Uri>,
+    pending: Mutex<VecDeque<(usize, S3Uri)>>,
+    finished: AtomicUsize,
+    local_dir: PathBuf,
+    cleanup: bool,
+    cleaned_up: AtomicBool,
+}
+
+/// Per-thread state for reading [ParquetFiles].
+///
+/// Each file is streamed a chunk at a time, so that only about a row group of
+/// it is held in memory at once.
+pub struct ParquetReader {
+    // Declared first to be dropped before the connection it streams from
+    current: Option<(QueryResult, PathBuf)>,
+    connection: Connection,
+}
+
+impl ParquetReader {
+    pub fn new(connection: Connection) -> Self {
+        Self {
+            current: None,
+            connection,
+        }
+    }
+}
+
+impl ParquetFiles {
+    /// List the files below `prefix`. With `cleanup`, they are deleted from S3
+    /// once all of them have been read or the scan is dropped.
+    pub async fn open(
+        s3_client: S3Client,
+        retry: RetryPolicy,
+        prefix: UnloadPrefix,
+        cleanup: bool,
+    ) -> Result<Self> {
+        let files = list_objects(&s3_client, &retry, &prefix.location).await?;
+        let local_dir = std::env::temp_dir()
+            .join("duckdb-athena-unload")
+            .join(&prefix.id);
+        tokio::fs::create_dir_all(&local_dir).await?;
+
+        let parquet = Self {
+            s3_client,
+            retry,
+            location: prefix.location,
+            pending: Mutex::new(files.iter().cloned().enumerate().collect()),
+            files,
+            finished: AtomicUsize::new(0),
+            local_dir,
+            cleanup,
+            cleaned_up: AtomicBool::new(false),
+        };
+        if parquet.files.is_empty() {
+            parquet.finish().await;
+        }
+
+        Ok(parquet)
+    }
+
+    pub fn num_files(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Share of the files read to the end, between 0 and 1.
+    pub fn fraction_finished(&self) -> f64 {
+        if self.files.is_empty() {
+            return 1.0;
+        }
+        self.finished.load(Ordering::SeqCst) as f64 / self.files.len() as f64
+    }
+
+    /// Whether every file has been read to the end.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst) == self.files.len()
+    }
+
+    /// The next chunk of rows for `reader`, which moves on to the next unclaimed
+    /// file as each one is exhausted.
+    pub async fn next_chunk(&self, reader: &mut ParquetReader) -> Result<Option<DataChunk>> {
+        loop {
+            if let Some((result, path)) = &mut reader.current {
+                let chunk = result
+                    .try_fetch_chunk()
+                    .map_err(|e| anyhow!("could not read {}: {}", path.display(), e))?;
+                if let Some(chunk) = chunk {
+                    return Ok(Some(chunk));
+                }
+                if let Some((_, path)) = reader.current.take() {
+                    tokio::fs::remove_file(path).await?;
+                }
+                if self.finished.fetch_add(1, Ordering::SeqCst) + 1 == self.files.len() {
+                    self.finish().await;
+                }
+            }
+
+            let next = self.pending.lock().expect("pending files lock").pop_front();
+            let (idx, file) = match next {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+
+            let path = self.local_dir.join(format!("part-{}.parquet", idx));
+            download_object(&self.s3_client, &self.retry, &file, &path).await?;
+
+            let sql = format!(
+                "SELECT * FROM read_parquet('{}')",
+                path.to_string_lossy().replace('\'', "''")
+            );
+            let result = reader
+                .connection
+                .query_streaming(&sql)
+                .map_err(|e| anyhow!("could not read {}: {}", file, e))?;
+            reader.current = Some((result, path));
+        }
+    }
+
+    /// Remove the local download directory and, with cleanup enabled, the files in S3.
+    ///
+    /// Only the first call does anything. Failures are logged rather than
+    /// returned, as they shouldn't fail a scan that has read its rows.
+    async fn finish(&self) {
+        if self.cleaned_up.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        if self.local_dir.exists() {
+            if let Err(e) = tokio::fs::remove_dir_all(&self.local_dir).await {
+                warn!("Could not remove {}: {}", self.local_dir.display(), e);
+            }
+        }
+        if self.cleanup && !self.files.is_empty() {
+            if let Err(e) = delete_objects(&self.s3_client, &self.retry, &self.files).await {
+                warn!(
+                    "Could not delete the UNLOAD files below {}: {}",
+                    self.location, e
+                );
+            }
+        }
+    }
+}
+
+impl Drop for ParquetFiles {
+    fn drop(&mut self) {
+        if self.cleaned_up.load(Ordering::SeqCst) {
+            return;
+        }
+        // On a thread of its own, as the scan may be dropped from within the runtime
+        std::thread::scope(|scope| {
+            scope.spawn(|| crate::RUNTIME.block_on(self.finish()));
+        });
+    }
+}